@@ -0,0 +1,149 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use chrono::{DateTime, Local, NaiveTime, TimeDelta};
+use serde::Deserialize;
+
+// タイムラインへの反応全体の上限。メンションへの返事には関係ない
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    // 0なら制限しない
+    pub per_hour: usize,
+    pub per_day: usize,
+    // この時間帯はタイムラインに反応しない
+    pub quiet_hours: Option<QuietHours>,
+}
+
+// "2:00-6:00"の形式。"23:00-5:00"のように日をまたいでもいい
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl TryFrom<String> for QuietHours {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok();
+        value
+            .split_once('-')
+            .and_then(|(start, end)| {
+                Some(Self {
+                    start: parse(start)?,
+                    end: parse(end)?,
+                })
+            })
+            .ok_or_else(|| format!("quiet_hours must be like \"2:00-6:00\": {value}"))
+    }
+}
+
+impl QuietHours {
+    fn contains(self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+pub struct Budget {
+    config: BudgetConfig,
+    // 直近1日の反応した日時(古い順)
+    spent: Mutex<VecDeque<DateTime<Local>>>,
+}
+
+impl Budget {
+    pub const fn new(config: BudgetConfig) -> Self {
+        Self {
+            config,
+            spent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    // 今タイムラインに反応していいか
+    pub fn allows(&self, now: DateTime<Local>) -> bool {
+        if self
+            .config
+            .quiet_hours
+            .is_some_and(|quiet| quiet.contains(now.time()))
+        {
+            return false;
+        }
+        let mut spent = self.spent.lock().unwrap();
+        while spent
+            .front()
+            .is_some_and(|at| now - *at >= TimeDelta::days(1))
+        {
+            spent.pop_front();
+        }
+        let last_hour = spent
+            .iter()
+            .filter(|at| now - **at < TimeDelta::hours(1))
+            .count();
+        (self.config.per_hour == 0 || last_hour < self.config.per_hour)
+            && (self.config.per_day == 0 || spent.len() < self.config.per_day)
+    }
+
+    pub fn spend(&self, now: DateTime<Local>) {
+        self.spent.lock().unwrap().push_back(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn per_hour() {
+        let budget = Budget::new(BudgetConfig {
+            per_hour: 2,
+            ..Default::default()
+        });
+        budget.spend(at(12, 0));
+        assert!(budget.allows(at(12, 10)));
+        budget.spend(at(12, 10));
+        assert!(!budget.allows(at(12, 20)));
+        assert!(budget.allows(at(13, 0)));
+    }
+
+    #[test]
+    fn per_day() {
+        let budget = Budget::new(BudgetConfig {
+            per_day: 2,
+            ..Default::default()
+        });
+        budget.spend(at(8, 0));
+        budget.spend(at(12, 0));
+        assert!(!budget.allows(at(20, 0)));
+        assert!(budget.allows(at(8, 0) + TimeDelta::days(1)));
+    }
+
+    #[test]
+    fn quiet_hours() {
+        let quiet = QuietHours::try_from("2:00-6:00".to_owned()).unwrap();
+        assert!(quiet.contains(at(2, 0).time()));
+        assert!(quiet.contains(at(5, 59).time()));
+        assert!(!quiet.contains(at(6, 0).time()));
+        assert!(!quiet.contains(at(1, 59).time()));
+
+        let overnight = QuietHours::try_from("23:00-5:00".to_owned()).unwrap();
+        assert!(overnight.contains(at(23, 30).time()));
+        assert!(overnight.contains(at(4, 0).time()));
+        assert!(!overnight.contains(at(12, 0).time()));
+
+        assert!(QuietHours::try_from("2時から6時".to_owned()).is_err());
+    }
+}
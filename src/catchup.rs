@@ -0,0 +1,197 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use chrono::{Local, TimeDelta};
+use serde::Deserialize;
+
+use crate::{
+    Sango, handler,
+    misskey::notes::{Mentions, Note},
+};
+
+// 1回のリクエストで取得する件数(APIの上限)
+const PAGE_SIZE: u32 = 100;
+// 重複して処理しないように覚えておくメンションの数
+const RECENT_CAPACITY: usize = 256;
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct CatchupConfig {
+    pub enabled: bool,
+    // これより古いメンション(秒)には反応しない
+    pub max_age: u64,
+}
+
+impl Default for CatchupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age: 60 * 60,
+        }
+    }
+}
+
+// ストリーミングと取りこぼし確認の両方から同じメンションが来ることがあるので、最近処理したものを覚えておく
+// タイムラインの投稿も、複数のチャンネルから同じものが来ることがあるので同じように使う
+#[derive(Default)]
+pub struct RecentIds(Mutex<VecDeque<String>>);
+
+impl RecentIds {
+    // 初めて見たIDならtrue
+    pub fn insert(&self, id: &str) -> bool {
+        let mut recent = self
+            .0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if recent.iter().any(|recent| recent == id) {
+            return false;
+        }
+        if recent.len() >= RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(id.to_owned());
+        true
+    }
+}
+
+// 取りこぼしを確かめている間は、ストリーミングで来たメンションでlastMentionIdを進めない
+// 先に進めてしまうと、確認が途中で失敗したときに間のメンションが二度と取れなくなる
+#[derive(Default)]
+pub struct MentionCursor(Mutex<CursorState>);
+
+#[derive(Default)]
+struct CursorState {
+    catching_up: bool,
+    // 確認中に来たメンションのうち一番新しいもの
+    pending: Option<String>,
+}
+
+impl MentionCursor {
+    fn lock(&self) -> std::sync::MutexGuard<'_, CursorState> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    pub fn pause(&self) {
+        self.lock().catching_up = true;
+    }
+
+    // 今lastMentionIdに書いていいならtrue。だめなら確認が終わるまで覚えておく
+    pub fn advance(&self, id: &str) -> bool {
+        let mut state = self.lock();
+        if !state.catching_up {
+            return true;
+        }
+        if state.pending.as_deref().is_none_or(|pending| pending < id) {
+            state.pending = Some(id.to_owned());
+        }
+        false
+    }
+
+    // 確認が終わったので、覚えておいたものを返して普段通りに戻す
+    fn resume(&self) -> Option<String> {
+        let mut state = self.lock();
+        state.catching_up = false;
+        state.pending.take()
+    }
+}
+
+// 切断されている間に来たメンションを取ってきて処理する
+// 失敗したら、次に接続したときの確認が成功するまでlastMentionIdは進まない
+pub async fn run(sango: &Sango, config: &CatchupConfig) -> anyhow::Result<()> {
+    if config.enabled {
+        catch_up(sango, config).await?;
+    }
+    if let Some(pending) = sango.mention_cursor.resume() {
+        sango
+            .savedata
+            .write()
+            .await
+            .update_last_mention_id(&pending)?;
+    }
+    Ok(())
+}
+
+async fn catch_up(sango: &Sango, config: &CatchupConfig) -> anyhow::Result<()> {
+    let Some(mut since_id) = sango.savedata.read().await.last_mention_id() else {
+        // 初回起動時は全部に反応してしまわないように何もしない
+        return Ok(());
+    };
+    let max_age = i64::try_from(config.max_age)
+        .ok()
+        .and_then(TimeDelta::try_seconds)
+        .unwrap_or(TimeDelta::MAX);
+
+    // 1ページずつ処理して、処理した分だけlastMentionIdを進める
+    loop {
+        let mut notes = sango
+            .client
+            .request(Mentions::since(&since_id, PAGE_SIZE))
+            .await?;
+        notes.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+        let Some(last) = notes.last() else {
+            return Ok(());
+        };
+        since_id.clone_from(&last.id);
+        let full = notes.len() >= PAGE_SIZE as usize;
+
+        let now = Local::now();
+        let (missed, too_old): (Vec<Note>, Vec<Note>) = notes
+            .into_iter()
+            .partition(|note| now - note.created_at <= max_age);
+        if !too_old.is_empty() {
+            log::info!("Skipping {} old mentions.", too_old.len());
+        }
+        if !missed.is_empty() {
+            log::info!("Catching up on {} missed mentions.", missed.len());
+        }
+        for note in missed {
+            handler::handle_mention(note, sango).await;
+        }
+        sango
+            .savedata
+            .write()
+            .await
+            .update_last_mention_id(&since_id)?;
+        if !full {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+    use crate::misskey::fake::{self, FakeClient};
+
+    #[tokio::test]
+    async fn failed_catch_up_keeps_cursor() {
+        let client = FakeClient::default();
+        client.fail::<Mentions>(StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR");
+        let sango = fake::sango(&client);
+        let last_mention_id = async || sango.savedata.read().await.last_mention_id();
+        sango
+            .savedata
+            .write()
+            .await
+            .update_last_mention_id("0")
+            .unwrap();
+        sango.mention_cursor.pause();
+        handler::handle_mention(fake::note("alice", "@sango こんにちは"), &sango).await;
+
+        // 確認が終わるまでは、ストリーミングで来たメンションで進めない
+        assert!(run(&sango, &CatchupConfig::default()).await.is_err());
+        assert_eq!(last_mention_id().await.as_deref(), Some("0"));
+
+        client.respond::<Mentions>(json!([]));
+        run(&sango, &CatchupConfig::default()).await.unwrap();
+        assert_eq!(last_mention_id().await.as_deref(), Some("note"));
+    }
+}
@@ -0,0 +1,109 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    config::Config,
+    misskey::{
+        ApiClient, MisskeyClient,
+        notes::{CreateNote, NoteVisibility},
+    },
+    rules::Rules,
+    savedata::{Backend, SaveData},
+};
+
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// 設定ファイルのパス
+    #[arg(long, default_value = "config.toml")]
+    pub config: PathBuf,
+
+    /// 保存データを置くディレクトリ
+    #[arg(long, default_value = ".")]
+    pub data_dir: PathBuf,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// BOTを起動する(省略時)
+    Run,
+    /// 設定ファイルとトークンを確認する
+    #[command(alias = "check-config")]
+    Check,
+    /// ノートを1件投稿して終了する
+    Post {
+        text: String,
+        #[arg(long, value_enum)]
+        visibility: Option<NoteVisibility>,
+    },
+    /// 保存されている呼び名を表示する
+    #[command(alias = "dump-data")]
+    DumpSavedata,
+    /// savedata.jsonの中身を設定した保存先に取り込む
+    ImportSavedata,
+}
+
+impl Cli {
+    // rules.tomlは設定ファイルと同じ場所に置く
+    pub fn rules_path(&self) -> PathBuf {
+        self.config.with_file_name("rules.toml")
+    }
+}
+
+pub async fn check(cli: &Cli) -> anyhow::Result<()> {
+    let conf = Config::load(&cli.config)?;
+    conf.validate()?;
+    println!("{}: OK", cli.config.display());
+
+    Rules::load(&cli.rules_path())?;
+    println!("{}: OK", cli.rules_path().display());
+
+    let client = MisskeyClient::new(&conf.base_url(), &conf.token, conf.rate_limit)?;
+    let self_id = client.get_id_self().await?;
+    println!("Authorized as {self_id}.");
+    Ok(())
+}
+
+pub async fn post(cli: &Cli, text: &str, visibility: Option<NoteVisibility>) -> anyhow::Result<()> {
+    let conf = Config::load(&cli.config)?;
+    let client: &dyn ApiClient =
+        &MisskeyClient::new(&conf.base_url(), &conf.token, conf.rate_limit)?;
+    let note = CreateNote {
+        visibility,
+        ..CreateNote::new(text)
+    };
+    client.request(note).await?;
+    println!("Posted.");
+    Ok(())
+}
+
+pub fn dump_savedata(cli: &Cli) -> anyhow::Result<()> {
+    let conf = Config::load(&cli.config)?;
+    let savedata = SaveData::open_read_only(conf.storage, &cli.data_dir)?;
+    let mut nicknames = savedata.nicknames()?;
+    nicknames.sort_unstable();
+    for (id, nick) in nicknames {
+        println!("{id}\t{nick}");
+    }
+    Ok(())
+}
+
+pub fn import_savedata(cli: &Cli) -> anyhow::Result<()> {
+    let conf = Config::load(&cli.config)?;
+    anyhow::ensure!(
+        matches!(conf.storage, Backend::Sqlite),
+        "storage is not set to \"sqlite\"; Nothing to import"
+    );
+    let mut savedata = SaveData::open(conf.storage, &cli.data_dir)?;
+    savedata.import_json(&cli.data_dir)?;
+    println!("Imported.");
+    Ok(())
+}
@@ -2,20 +2,106 @@
 //
 // SPDX-License-Identifier: UPL-1.0
 
+use std::path::Path;
+
 use anyhow::Context;
 use serde::Deserialize;
 
+use crate::{
+    budget::BudgetConfig,
+    catchup::CatchupConfig,
+    connection::ReconnectConfig,
+    cooldown::CooldownConfig,
+    handler::admin::AdminConfig,
+    handler::registry::HandlersConfig,
+    handler::unfollow::UnfollowConfig,
+    misskey::ratelimit::RateLimitConfig,
+    roles::RolesConfig,
+    savedata::Backend,
+    schedule::ScheduledPost,
+    wakeup::WakeupConfig,
+    websocket::{self, ChannelConfig},
+};
+
 #[derive(Deserialize)]
 pub struct Config {
     pub token: String,
     pub host: String,
     pub admin: String,
+    #[serde(default)]
+    pub storage: Backend,
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    #[serde(default)]
+    pub wakeup: WakeupConfig,
+    #[serde(default)]
+    pub catchup: CatchupConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub handlers: HandlersConfig,
+    #[serde(default)]
+    pub cooldown: CooldownConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub admin_commands: AdminConfig,
+    #[serde(default)]
+    pub roles: RolesConfig,
+    #[serde(default = "websocket::default_channels")]
+    pub channels: Vec<ChannelConfig>,
+    #[serde(default)]
+    pub unfollow: UnfollowConfig,
+    #[serde(default)]
+    pub schedule: Vec<ScheduledPost>,
 }
 
 impl Config {
-    pub fn load() -> anyhow::Result<Self> {
-        let file = std::fs::read_to_string("config.toml").context("Failed to load config")?;
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::read_to_string(path).context("Failed to load config")?;
         let config = toml::from_str(&file).context("Failed to parse config")?;
         Ok(config)
     }
+
+    // ホスト名だけならhttpsで繋ぐ。手元のサーバーなどは"http://localhost:3000"のようにも書ける
+    pub fn base_url(&self) -> String {
+        if self.host.starts_with("https://") || self.host.starts_with("http://") {
+            self.host.clone()
+        } else {
+            format!("https://{}", self.host)
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.host.is_empty(), "host is empty");
+        let host = self
+            .host
+            .strip_prefix("https://")
+            .or_else(|| self.host.strip_prefix("http://"))
+            .unwrap_or(&self.host);
+        anyhow::ensure!(
+            !host.is_empty() && !host.contains("://") && !host.contains('/'),
+            "host must be a hostname like \"example.com\""
+        );
+        anyhow::ensure!(!self.token.is_empty(), "token is empty");
+        anyhow::ensure!(!self.admin.is_empty(), "admin is empty");
+        anyhow::ensure!(
+            self.unfollow.interval > 0,
+            "unfollow.interval must be positive"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::misskey::fake;
+
+    #[test]
+    fn zero_unfollow_interval() {
+        let mut config = fake::config();
+        assert!(config.validate().is_ok());
+        config.unfollow.interval = 0;
+        assert!(config.validate().is_err());
+    }
 }
@@ -0,0 +1,79 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeDelta};
+use serde::Deserialize;
+
+// これより長く繋がっていたら、次に切れたときは最初の待ち時間からやり直す
+const STABLE_AFTER: Duration = Duration::from_mins(1);
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct ReconnectConfig {
+    // 秒
+    pub initial_delay: u64,
+    pub max_delay: u64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: 1,
+            max_delay: 300,
+        }
+    }
+}
+
+// 再接続の状況。BOTのどこからでも読めるようにSangoに持たせる
+#[derive(Default)]
+pub struct ConnectionState {
+    // 最後に接続できてから失敗した回数
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub connected_since: Option<DateTime<Local>>,
+    pub disconnected_at: Option<DateTime<Local>>,
+    ever_connected: bool,
+}
+
+impl ConnectionState {
+    // 再接続なら切断されていた時間を返す
+    pub fn connected(&mut self) -> Option<TimeDelta> {
+        let now = Local::now();
+        self.connected_since = Some(now);
+        let downtime = self.disconnected_at.take().map(|at| now - at);
+        if std::mem::replace(&mut self.ever_connected, true) {
+            downtime.or(Some(TimeDelta::zero()))
+        } else {
+            None
+        }
+    }
+
+    pub fn failed(&mut self, error: &anyhow::Error) {
+        let now = Local::now();
+        if self
+            .connected_since
+            .take()
+            .is_some_and(|since| (now - since).to_std().unwrap_or_default() >= STABLE_AFTER)
+        {
+            self.attempts = 0;
+        }
+        self.attempts = self.attempts.saturating_add(1);
+        self.last_error = Some(format!("{error:#}"));
+        self.disconnected_at.get_or_insert(now);
+    }
+}
+
+// 指数的に伸ばした待ち時間の半分から全部までの間でランダムに待つ
+pub fn backoff(config: ReconnectConfig, attempts: u32) -> Duration {
+    let max = config.max_delay.max(1).saturating_mul(1000);
+    let exp = config
+        .initial_delay
+        .max(1)
+        .saturating_mul(1000)
+        .saturating_mul(1 << attempts.saturating_sub(1).min(31))
+        .min(max);
+    Duration::from_millis(rand::random_range(exp / 2..=exp))
+}
@@ -0,0 +1,218 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::{DateTime, Local, TimeDelta};
+use serde::Deserialize;
+
+// タイムラインへの反応を、同じ相手や同じ内容ばかりにしないための待ち時間
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct CooldownConfig {
+    // 秒。0なら待たない
+    pub user: u64,    // 同じ人への反応
+    pub handler: u64, // 同じ反応(誰に対してでも)
+    pub pair: u64,    // 同じ人への同じ反応
+    // 再起動しても覚えておく
+    pub persist: bool,
+}
+
+impl Default for CooldownConfig {
+    fn default() -> Self {
+        Self {
+            user: 60,
+            handler: 0,
+            pair: 600,
+            persist: false,
+        }
+    }
+}
+
+// 保存するときは、反応のたびではなくこの間隔をあけて書き込む
+const SAVE_INTERVAL: TimeDelta = TimeDelta::seconds(60);
+
+// キーごとに最後に反応した日時を覚えておく
+// キーは"user:<ユーザーID>"、"handler:<反応の名前>"、"pair:<反応の名前>:<ユーザーID>"
+pub struct Cooldowns {
+    config: CooldownConfig,
+    last: Mutex<HashMap<String, DateTime<Local>>>,
+    saved_at: Mutex<Option<DateTime<Local>>>,
+}
+
+impl Cooldowns {
+    pub fn new(config: CooldownConfig, saved: HashMap<String, DateTime<Local>>) -> Self {
+        let saved = if config.persist {
+            saved
+        } else {
+            HashMap::new()
+        };
+        Self {
+            config,
+            last: Mutex::new(saved),
+            saved_at: Mutex::new(None),
+        }
+    }
+
+    pub const fn persist(&self) -> bool {
+        self.config.persist
+    }
+
+    // 反応していいか確かめて、いいならその時刻を記録する
+    pub fn try_start(&self, user_id: &str, handler: &str, now: DateTime<Local>) -> bool {
+        let keys = [
+            (format!("user:{user_id}"), self.config.user),
+            (format!("handler:{handler}"), self.config.handler),
+            (format!("pair:{handler}:{user_id}"), self.config.pair),
+        ];
+        let mut last = self.last.lock().unwrap();
+        self.prune(&mut last, now);
+        let cooling = keys
+            .iter()
+            .any(|(key, secs)| last.get(key).is_some_and(|at| now - *at < duration(*secs)));
+        if cooling {
+            return false;
+        }
+        for (key, secs) in keys {
+            if secs > 0 {
+                last.insert(key, now);
+            }
+        }
+        true
+    }
+
+    // 保存する中身
+    pub fn snapshot(&self, now: DateTime<Local>) -> HashMap<String, DateTime<Local>> {
+        let mut last = self.last.lock().unwrap();
+        self.prune(&mut last, now);
+        last.clone()
+    }
+
+    // 前回の保存からSAVE_INTERVAL経っていれば、保存する中身
+    pub fn snapshot_to_save(
+        &self,
+        now: DateTime<Local>,
+    ) -> Option<HashMap<String, DateTime<Local>>> {
+        if !self.config.persist {
+            return None;
+        }
+        let mut saved_at = self.saved_at.lock().unwrap();
+        if saved_at.is_some_and(|at| now - at < SAVE_INTERVAL) {
+            return None;
+        }
+        *saved_at = Some(now);
+        drop(saved_at);
+        Some(self.snapshot(now))
+    }
+
+    // 一番長い待ち時間も過ぎたものは捨てる
+    fn prune(&self, last: &mut HashMap<String, DateTime<Local>>, now: DateTime<Local>) {
+        let longest = duration(
+            self.config
+                .user
+                .max(self.config.handler)
+                .max(self.config.pair),
+        );
+        last.retain(|_, at| now - *at < longest);
+    }
+}
+
+// 大きすぎる値は、ずっと待つものとして扱う
+fn duration(secs: u64) -> TimeDelta {
+    i64::try_from(secs)
+        .ok()
+        .and_then(TimeDelta::try_seconds)
+        .unwrap_or(TimeDelta::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cooldowns(user: u64, handler: u64, pair: u64) -> Cooldowns {
+        let config = CooldownConfig {
+            user,
+            handler,
+            pair,
+            persist: false,
+        };
+        Cooldowns::new(config, HashMap::new())
+    }
+
+    #[test]
+    fn user() {
+        let cooldowns = cooldowns(60, 0, 0);
+        let now = Local::now();
+        assert!(cooldowns.try_start("alice", "sleepy", now));
+        assert!(!cooldowns.try_start("alice", "tired", now + TimeDelta::seconds(30)));
+        assert!(cooldowns.try_start("bob", "sleepy", now + TimeDelta::seconds(30)));
+        assert!(cooldowns.try_start("alice", "tired", now + TimeDelta::seconds(60)));
+    }
+
+    #[test]
+    fn handler() {
+        let cooldowns = cooldowns(0, 60, 0);
+        let now = Local::now();
+        assert!(cooldowns.try_start("alice", "sleepy", now));
+        assert!(!cooldowns.try_start("bob", "sleepy", now));
+        assert!(cooldowns.try_start("bob", "tired", now));
+    }
+
+    #[test]
+    fn pair() {
+        let cooldowns = cooldowns(0, 0, 60);
+        let now = Local::now();
+        assert!(cooldowns.try_start("alice", "sleepy", now));
+        assert!(!cooldowns.try_start("alice", "sleepy", now));
+        assert!(cooldowns.try_start("alice", "tired", now));
+        assert!(cooldowns.try_start("bob", "sleepy", now));
+    }
+
+    #[test]
+    fn snapshot_drops_expired() {
+        let cooldowns = cooldowns(60, 0, 600);
+        let now = Local::now();
+        cooldowns.try_start("alice", "sleepy", now);
+        assert_eq!(cooldowns.snapshot(now + TimeDelta::seconds(120)).len(), 2);
+        assert!(cooldowns.snapshot(now + TimeDelta::seconds(600)).is_empty());
+    }
+
+    #[test]
+    fn try_start_drops_expired() {
+        let cooldowns = cooldowns(60, 0, 0);
+        let now = Local::now();
+        cooldowns.try_start("alice", "sleepy", now);
+        cooldowns.try_start("bob", "sleepy", now + TimeDelta::seconds(120));
+        assert_eq!(cooldowns.last.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn huge_duration() {
+        let cooldowns = cooldowns(u64::MAX, 0, 0);
+        let now = Local::now();
+        assert!(cooldowns.try_start("alice", "sleepy", now));
+        assert!(!cooldowns.try_start("alice", "sleepy", now + TimeDelta::days(365)));
+    }
+
+    #[test]
+    fn save_interval() {
+        let config = CooldownConfig {
+            persist: true,
+            ..Default::default()
+        };
+        let cooldowns = Cooldowns::new(config, HashMap::new());
+        let now = Local::now();
+        assert!(cooldowns.snapshot_to_save(now).is_some());
+        assert!(
+            cooldowns
+                .snapshot_to_save(now + TimeDelta::seconds(30))
+                .is_none()
+        );
+        assert!(
+            cooldowns
+                .snapshot_to_save(now + TimeDelta::seconds(60))
+                .is_some()
+        );
+    }
+}
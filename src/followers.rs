@@ -0,0 +1,131 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+// フォローをやめられたことに気づくための見回り
+// Misskey本体はフォローをやめた側にしかunfollowのイベントを送らないので、フォロワーの一覧を前回と比べる
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use crate::{
+    Sango,
+    misskey::users::{Followers, User},
+};
+
+// 1回のリクエストで取得する件数(APIの上限)
+const PAGE_SIZE: u32 = 100;
+// これより多いと一覧を取り切れないので比べない
+const MAX_PAGES: usize = 100;
+
+pub async fn run(sango: Arc<Sango>, interval: u64) {
+    let mut known = None;
+    loop {
+        // unfollowの反応がどれも無効なら、取りに行かない
+        if sango.handlers().unfollow.has_enabled() {
+            match poll(&sango, &mut known).await {
+                Ok(gone) => {
+                    for user in gone {
+                        log::info!("{} has unfollowed.", user.mention());
+                        let sango = Arc::clone(&sango);
+                        tokio::spawn(async move {
+                            sango.handlers().unfollow.handle(&user, &sango).await;
+                        });
+                    }
+                }
+                Err(e) => log::error!("Failed to check followers: {e}"),
+            }
+        } else {
+            known = None;
+        }
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+// 前回からいなくなったフォロワー。初回は覚えるだけ
+async fn poll(
+    sango: &Sango,
+    known: &mut Option<HashMap<String, User>>,
+) -> anyhow::Result<Vec<User>> {
+    let followers = fetch(sango).await?;
+    let gone = known.take().map_or_else(Vec::new, |previous| {
+        previous
+            .into_values()
+            .filter(|user| !followers.contains_key(&user.id))
+            .collect()
+    });
+    *known = Some(followers);
+    Ok(gone)
+}
+
+async fn fetch(sango: &Sango) -> anyhow::Result<HashMap<String, User>> {
+    let mut followers = HashMap::new();
+    let mut until_id = None;
+    for _ in 0..MAX_PAGES {
+        let page = sango
+            .client
+            .request(Followers::new(
+                &sango.self_id,
+                PAGE_SIZE,
+                until_id.as_deref(),
+            ))
+            .await?;
+        let full = page.len() >= PAGE_SIZE as usize;
+        until_id = page.last().map(|following| following.id.clone());
+        followers.extend(
+            page.into_iter()
+                .map(|following| (following.follower.id.clone(), following.follower)),
+        );
+        if !full {
+            return Ok(followers);
+        }
+    }
+    anyhow::bail!("Too many followers to check")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        misskey::fake::{self, FakeClient},
+        mock,
+    };
+
+    fn followers(client: &FakeClient, ids: &[&str]) {
+        let page: Vec<_> = ids
+            .iter()
+            .map(|id| json!({ "id": format!("following-{id}"), "follower": mock::user(id, id, false) }))
+            .collect();
+        client.respond::<Followers>(page.into());
+    }
+
+    #[tokio::test]
+    async fn gone() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let mut known = None;
+        followers(&client, &["alice", "bob"]);
+        assert!(poll(&sango, &mut known).await.unwrap().is_empty());
+
+        followers(&client, &["bob", "carol"]);
+        let gone = poll(&sango, &mut known).await.unwrap();
+        assert_eq!(gone.len(), 1);
+        assert_eq!(gone[0].id, "alice");
+    }
+
+    #[tokio::test]
+    async fn failed() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let mut known = None;
+        followers(&client, &["alice"]);
+        poll(&sango, &mut known).await.unwrap();
+
+        // 取れなかったときは前回のものを覚えたままにする
+        client.fail::<Followers>(reqwest::StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR");
+        assert!(poll(&sango, &mut known).await.is_err());
+        followers(&client, &[]);
+        assert_eq!(poll(&sango, &mut known).await.unwrap().len(), 1);
+    }
+}
@@ -2,23 +2,46 @@
 //
 // SPDX-License-Identifier: UPL-1.0
 
-use std::sync::Arc;
+use std::{future::Future, sync::Arc};
 
 use crate::{
     Sango,
-    handler::{mention::HandleMention, note::HandleNote},
-    misskey::{notes::Note, users::User},
+    config::Config,
+    handler::{mention::HandleMention, note::HandleNote, registry::Registry},
+    misskey::{
+        chat::{ChatMessage, CreateChatMessage, CreateChatReaction},
+        error::MisskeyError,
+        notes::{CreateNote, DeleteReaction, Note},
+        ratelimit::{self, Priority},
+        users::User,
+    },
+    roles::Role,
+    rules::Rules,
     websocket::{EventBody, EventBodyType},
 };
 
+pub mod admin;
+mod follow_request;
 mod followed;
-mod mention;
+pub mod mention;
 mod note;
+pub mod registry;
+mod renote;
+mod rule;
+pub mod unfollow;
 
-pub trait Handler {
+// 役割が足りない人への返事
+pub const DENIED: &str = "この機能は使える人が限られてるんだ。ゴメンね";
+
+// Registryに入れて別タスクで動かすので、返すFutureはSendにしておく
+// 実装するときは今まで通りasync fnで書ける
+pub trait Handler: Sync {
     // 反応する単語
     const KEYWORDS: &[&str] = &[];
 
+    // 使うのに必要な役割。足りない人にはDENIEDを返す
+    const ROLE: Role = Role::Everyone;
+
     // 追加条件
     fn cond(&self, _note: &Note) -> bool {
         true
@@ -31,35 +54,201 @@ pub trait Handler {
         keyword_check && self.cond(note)
     }
 
-    // `action`、`respond`、`RESPONSE`のいずれかを実装する
+    // `action`、`respond`、`RESPONSE`、`reaction`、`REACTION`のいずれかを実装する
+    // リアクションと返信は両方してもいい
     const RESPONSE: &str = "";
-    async fn respond(&self, _note: &Note, _sango: &Sango) -> anyhow::Result<String> {
-        Ok(Self::RESPONSE.to_owned())
+    fn respond(
+        &self,
+        _note: &Note,
+        _sango: &Sango,
+    ) -> impl Future<Output = anyhow::Result<String>> + Send {
+        async { Ok(Self::RESPONSE.to_owned()) }
     }
-    async fn action(&self, note: &Note, sango: &Sango) -> anyhow::Result<()> {
-        let response = self.respond(note, sango).await?;
-        if !response.is_empty() {
-            sango.client.request(note.reply(&response)).await?;
+    const REACTION: &str = "";
+    fn reaction(&self, _note: &Note) -> String {
+        Self::REACTION.to_owned()
+    }
+    fn action(
+        &self,
+        note: &Note,
+        sango: &Sango,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send {
+        async move {
+            let reaction = self.reaction(note);
+            if !reaction.is_empty() {
+                react(note, &reaction, sango).await?;
+            }
+            let response = self.respond(note, sango).await?;
+            if !response.is_empty() {
+                reply(note, &response, sango).await?;
+            }
+            Ok(())
         }
-        Ok(())
     }
 
     // 上書きしない
-    async fn handle(&self, note: &Note, sango: &Sango) -> bool {
-        if self.gate(note, sango) {
-            if let Err(e) = self.action(note, sango).await {
-                log::error!("{e}");
+    fn handle(&self, note: &Note, sango: &Sango) -> impl Future<Output = bool> + Send {
+        async move {
+            if self.gate(note, sango) {
+                if let Err(e) = run(self, note, sango).await {
+                    log::error!("{e}");
+                }
+                true
+            } else {
+                false
             }
-            true
-        } else {
-            false
+        }
+    }
+}
+
+// 役割を確かめてからactionを動かす
+async fn run<H: Handler + ?Sized>(handler: &H, note: &Note, sango: &Sango) -> anyhow::Result<()> {
+    if sango.roles().has(&note.user, H::ROLE) {
+        handler.action(note, sango).await
+    } else {
+        reply(note, DENIED, sango).await
+    }
+}
+
+// チャットならチャットで、ノートならリプライで返事する
+pub async fn reply(note: &Note, text: &str, sango: &Sango) -> anyhow::Result<()> {
+    if note.chat {
+        sango
+            .client
+            .request(CreateChatMessage::new(&note.user_id, text))
+            .await?;
+    } else {
+        sango.client.request(note.reply(text)).await?;
+    }
+    Ok(())
+}
+
+// 既に別の絵文字でリアクションしていたら付け直す
+pub async fn react(note: &Note, reaction: &str, sango: &Sango) -> anyhow::Result<()> {
+    if note.chat {
+        sango
+            .client
+            .request(CreateChatReaction::new(&note.id, reaction))
+            .await?;
+        return Ok(());
+    }
+    let Err(e) = sango.client.request(note.react(reaction)).await else {
+        return Ok(());
+    };
+    if !matches!(e.downcast_ref(), Some(MisskeyError::AlreadyReacted)) {
+        return Err(e);
+    }
+    sango.client.request(DeleteReaction::new(&note.id)).await?;
+    sango.client.request(note.react(reaction)).await?;
+    Ok(())
+}
+
+// フォローなど、ユーザーについてのイベントへの反応
+pub trait UserHandler: Sync {
+    // 追加条件
+    fn cond(&self, _user: &User) -> bool {
+        true
+    }
+
+    fn gate(&self, user: &User, sango: &Sango) -> bool {
+        !user.is_bot // BOTを無視
+        && user.id != sango.self_id // 自身を無視
+        && self.cond(user)
+    }
+
+    // `action`、`respond`、`RESPONSE`のいずれかを実装する
+    // 返事はリプライではなく普通のノートとして投稿する
+    const RESPONSE: &str = "";
+    fn respond(
+        &self,
+        _user: &User,
+        _sango: &Sango,
+    ) -> impl Future<Output = anyhow::Result<String>> + Send {
+        async { Ok(Self::RESPONSE.to_owned()) }
+    }
+    fn action(
+        &self,
+        user: &User,
+        sango: &Sango,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send {
+        async move {
+            let response = self.respond(user, sango).await?;
+            if !response.is_empty() {
+                sango.client.request(CreateNote::new(&response)).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// イベントごとの反応の一覧
+pub struct Handlers {
+    pub mention: Registry<Note>,
+    // タイムラインの投稿への反応。config.tomlの[[channels]]の順に1つずつ
+    pub timelines: Vec<Registry<Note>>,
+    pub followed: Registry<User>,
+    pub follow_request: Registry<User>,
+    pub unfollow: Registry<User>,
+    pub renote: Registry<Note>, // 本文のないリノート
+    pub quote: Registry<Note>,  // 本文付きのリノート
+}
+
+impl Handlers {
+    pub fn new(rules: Rules, config: &Config) -> Self {
+        let mut mention = Registry::default();
+        mention::register(&mut mention, rules.mention, config.admin_commands);
+        let channels = &config.channels;
+        let unfollow_config = config.unfollow.clone();
+        let config = &config.handlers;
+        mention.configure(&config.mention);
+
+        let timelines = channels
+            .iter()
+            .map(|channel| {
+                let mut timeline = Registry::default();
+                note::register(&mut timeline, rules.note.clone());
+                timeline.configure(&config.note);
+                if let Some(names) = &channel.handlers {
+                    timeline.retain(names);
+                }
+                timeline
+            })
+            .collect();
+
+        let mut followed = Registry::default();
+        followed::register(&mut followed);
+        followed.configure(&config.followed);
+
+        let mut follow_request = Registry::default();
+        follow_request::register(&mut follow_request);
+        follow_request.configure(&config.follow_request);
+
+        let mut unfollow = Registry::default();
+        unfollow::register(&mut unfollow, unfollow_config);
+        unfollow.configure(&config.unfollow);
+
+        let mut renote = Registry::default();
+        let mut quote = Registry::default();
+        renote::register(&mut renote, &mut quote);
+        renote.configure(&config.renote);
+        quote.configure(&config.quote);
+
+        Self {
+            mention,
+            timelines,
+            followed,
+            follow_request,
+            unfollow,
+            renote,
+            quote,
         }
     }
 }
 
 pub async fn handle(event: EventBody, sango: Arc<Sango>) {
     match event.event_type {
-        EventBodyType::Followed => {
+        // フォローをやめられたことは、イベントではなくfollowersの見回りで気づく
+        EventBodyType::Followed | EventBodyType::ReceiveFollowRequest => {
             let user: User = match serde_json::from_value(event.body) {
                 Ok(user) => user,
                 Err(e) => {
@@ -67,8 +256,14 @@ pub async fn handle(event: EventBody, sango: Arc<Sango>) {
                     return;
                 }
             };
-            log::debug!("Received a follow.");
-            followed::on_follow(user, &sango).await;
+            let handlers = sango.handlers();
+            let registry = if matches!(event.event_type, EventBodyType::Followed) {
+                &handlers.followed
+            } else {
+                &handlers.follow_request
+            };
+            log::debug!("Received {:?}.", event.event_type);
+            registry.handle(&user, &sango).await;
         }
         EventBodyType::Mention => {
             let note: Note = match serde_json::from_value(event.body) {
@@ -79,7 +274,53 @@ pub async fn handle(event: EventBody, sango: Arc<Sango>) {
                 }
             };
             log::debug!("Received a mention.");
-            HandleMention.handle(&note, &sango).await;
+            handle_mention(note, &sango).await;
+        }
+        EventBodyType::Reply => {
+            let note: Note = match serde_json::from_value(event.body) {
+                Ok(note) => note,
+                Err(e) => {
+                    log::error!("{e}");
+                    return;
+                }
+            };
+            log::debug!("Received a reply.");
+            handle_reply(note, &sango).await;
+        }
+        EventBodyType::Renote => {
+            let note: Note = match serde_json::from_value(event.body) {
+                Ok(note) => note,
+                Err(e) => {
+                    log::error!("{e}");
+                    return;
+                }
+            };
+            let handlers = sango.handlers();
+            let registry = if note.is_quote() {
+                &handlers.quote
+            } else {
+                &handlers.renote
+            };
+            log::debug!("Received a renote.");
+            registry.handle(&note, &sango).await;
+        }
+        EventBodyType::NewChatMessage => {
+            let message: ChatMessage = match serde_json::from_value(event.body) {
+                Ok(message) => message,
+                Err(e) => {
+                    log::error!("{e}");
+                    return;
+                }
+            };
+            // ルームのメッセージには反応しない
+            if message.to_room_id.is_some() {
+                return;
+            }
+            let Some(note) = message.into_note() else {
+                return;
+            };
+            log::debug!("Received a chat message.");
+            ratelimit::with_priority(Priority::High, HandleMention.handle(&note, &sango)).await;
         }
         EventBodyType::Note => {
             let note: Note = match serde_json::from_value(event.body) {
@@ -89,9 +330,136 @@ pub async fn handle(event: EventBody, sango: Arc<Sango>) {
                     return;
                 }
             };
-            log::debug!("Received a note.");
-            HandleNote.handle(&note, &sango).await;
+            // mainチャンネルからは来ないはず
+            let Ok(channel) = event.id.parse() else {
+                return;
+            };
+            log::debug!("Received a note from channel {channel}.");
+            HandleNote(channel).handle(&note, &sango).await;
         }
         _ => {}
     }
 }
+
+// BOTのノートへのリプライは、キーワードがなくてもメンションと同じように話しかけられたものとして扱う
+// メンションとしても来ることが多いので、どちらで来てもノートのIDで1回だけ処理する
+pub async fn handle_reply(note: Note, sango: &Sango) {
+    if note.mentions.contains(&sango.self_id) {
+        handle_mention(note, sango).await;
+        return;
+    }
+    if !sango.recent_mentions.insert(&note.id) {
+        log::debug!("Skipping an already handled reply.");
+        return;
+    }
+    ratelimit::with_priority(Priority::High, HandleMention.handle(&note, sango)).await;
+}
+
+// ストリーミングと取りこぼし確認の両方からここに来る
+pub async fn handle_mention(note: Note, sango: &Sango) {
+    if !sango.recent_mentions.insert(&note.id) {
+        log::debug!("Skipping an already handled mention.");
+        return;
+    }
+    if sango.mention_cursor.advance(&note.id) {
+        let updated = sango
+            .savedata
+            .write()
+            .await
+            .update_last_mention_id(&note.id);
+        if let Err(e) = updated {
+            log::error!("{e}");
+        }
+    }
+    // メンションへの返信はタイムラインへの反応より先に送る
+    ratelimit::with_priority(Priority::High, HandleMention.handle(&note, sango)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::misskey::fake::{self, FakeClient};
+
+    // ルールをrules.tomlに移す前の、組み込みの反応の順番
+    #[test]
+    fn baseline_precedence() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let handlers = sango.handlers();
+        let added = ["admin", "optOut", "optIn", "conversation"];
+        let mention: Vec<_> = handlers
+            .mention
+            .names()
+            .into_iter()
+            .filter(|name| !added.contains(name))
+            .collect();
+        assert_eq!(
+            mention,
+            [
+                "follow",
+                "unfollow",
+                "aiScream1",
+                "aiScream2",
+                "speedtest",
+                "todo",
+                "meet",
+                "hello",
+                "intro",
+                "pat",
+                "meow",
+                "time",
+                "insult",
+                "chikuwa",
+                "ping",
+                "setNickname",
+                "forgetNickname",
+            ]
+        );
+        assert_eq!(
+            handlers.timelines[0].names(),
+            [
+                "pain",
+                "tired",
+                "goWork",
+                "leaveWork",
+                "nullpo",
+                "call",
+                "sleepy",
+                "goodMorning",
+                "goodNight",
+                "lateMorning",
+                "meow",
+                "sleepAgain",
+            ]
+        );
+    }
+
+    #[test]
+    fn keyword_precedence() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let handlers = sango.handlers();
+        let mention = |text| {
+            let note = fake::note("alice", text);
+            handlers
+                .mention
+                .find(&note, &sango)
+                .map(|entry| entry.name().to_owned())
+        };
+        assert_eq!(mention("今何時？罵って").as_deref(), Some("time"));
+        assert_eq!(mention("ping 今何時").as_deref(), Some("time"));
+        assert_eq!(mention("にゃーん 今何時").as_deref(), Some("meow"));
+        assert_eq!(mention("罵ってって呼んで").as_deref(), Some("insult"));
+
+        let timeline = |text| {
+            let note = fake::note("alice", text);
+            handlers.timelines[0]
+                .find(&note, &sango)
+                .map(|entry| entry.name().to_owned())
+        };
+        assert_eq!(timeline("眠い").as_deref(), Some("sleepy"));
+        assert_eq!(timeline("眠いけど寒くない").as_deref(), Some("sleepy"));
+        assert_eq!(timeline("ねむいけど寒くない"), None);
+        assert_eq!(timeline("寒くないけどねむい"), None);
+        assert_eq!(timeline("つかれた、眠い").as_deref(), Some("tired"));
+    }
+}
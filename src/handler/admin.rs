@@ -0,0 +1,388 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+// モデレーターと管理者が使えるコマンド。"@sango /reload"のようにメンションで送る
+
+use std::sync::{LazyLock, atomic::Ordering};
+
+use chrono::{Local, TimeDelta};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    NEEDS_RESTART, RELOADED, Sango,
+    handler::{DENIED, Handler, reply},
+    misskey::{
+        error::MisskeyError,
+        following::{CreateFollowing, DeleteFollowing},
+        notes::{CreateNote, Note, NoteVisibility},
+        users::ShowUser,
+    },
+    roles::Role,
+};
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct AdminConfig {
+    // trueならダイレクト(specified)で送られたコマンドだけ受け付ける
+    pub specified_only: bool,
+}
+
+enum Command<'a> {
+    Reload,
+    Mute,
+    Unmute,
+    Follow(&'a str), // "@user@host"または"@user"
+    Unfollow(&'a str),
+    Stats,
+    Announce(&'a str),
+    Shutdown,
+}
+
+// 先頭のメンションを飛ばして、"/command 引数"を取り出す
+static COMMAND: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)^\s*(?:@\S+\s+)*/(\w+)\s*(.*?)\s*$").unwrap());
+
+fn parse_command(text: &str) -> Option<Command<'_>> {
+    let cap = COMMAND.captures(text)?;
+    let arg = cap.get(2).map_or("", |arg| arg.as_str());
+    let command = match &cap[1] {
+        "reload" => Command::Reload,
+        "mute" => Command::Mute,
+        "unmute" => Command::Unmute,
+        "follow" if !arg.is_empty() => Command::Follow(arg),
+        "unfollow" if !arg.is_empty() => Command::Unfollow(arg),
+        "stats" => Command::Stats,
+        "announce" if !arg.is_empty() => Command::Announce(arg),
+        "shutdown" => Command::Shutdown,
+        _ => return None,
+    };
+    Some(command)
+}
+
+impl Command<'_> {
+    // ミュートや状態の確認はモデレーターでも、それ以外は管理者だけ
+    const fn role(&self) -> Role {
+        match self {
+            Self::Mute | Self::Unmute | Self::Stats => Role::Moderator,
+            Self::Reload
+            | Self::Follow(_)
+            | Self::Unfollow(_)
+            | Self::Announce(_)
+            | Self::Shutdown => Role::Admin,
+        }
+    }
+}
+
+// "@user@host" -> ("user", Some("host"))
+fn parse_acct(acct: &str) -> (&str, Option<&str>) {
+    let acct = acct.strip_prefix('@').unwrap_or(acct);
+    match acct.split_once('@') {
+        Some((username, host)) => (username, Some(host)),
+        None => (acct, None),
+    }
+}
+
+pub struct HandleAdmin(pub AdminConfig);
+impl Handler for HandleAdmin {
+    const ROLE: Role = Role::Moderator;
+
+    fn gate(&self, note: &Note, _sango: &Sango) -> bool {
+        parse_command(&note.text).is_some()
+    }
+
+    async fn action(&self, note: &Note, sango: &Sango) -> anyhow::Result<()> {
+        let Some(command) = parse_command(&note.text) else {
+            return Ok(());
+        };
+        let allowed = sango.roles().has(&note.user, command.role());
+        let response = if !allowed {
+            DENIED.to_owned()
+        } else if self.0.specified_only && !matches!(note.visibility, NoteVisibility::Specified) {
+            "そういうお願いは、ダイレクトでこっそり送ってね".to_owned()
+        } else {
+            run(&command, sango).await?
+        };
+        reply(note, &response, sango).await?;
+
+        // 返事をしてから止まる
+        if allowed && matches!(command, Command::Shutdown) {
+            log::info!("Shutdown requested by the admin.");
+            sango.shutdown.notify_one();
+        }
+        Ok(())
+    }
+}
+
+async fn run(command: &Command<'_>, sango: &Sango) -> anyhow::Result<String> {
+    let response = match command {
+        Command::Reload => match sango.reload() {
+            Ok(()) => format!(
+                "読み込み直したよ: {}\nこれは再起動しないと変わらないよ: {}",
+                RELOADED.join("、"),
+                NEEDS_RESTART.join("、")
+            ),
+            Err(e) => format!("読み込めなかった……\n{e:#}"),
+        },
+        Command::Mute | Command::Unmute => {
+            let muted = matches!(command, Command::Mute);
+            sango.muted.store(muted, Ordering::Relaxed);
+            sango.savedata.write().await.set_muted(muted)?;
+            if muted {
+                "しばらくタイムラインには反応しないでおくね".to_owned()
+            } else {
+                "またタイムラインに反応するね".to_owned()
+            }
+        }
+        Command::Follow(acct) => follow(acct, true, sango).await?,
+        Command::Unfollow(acct) => follow(acct, false, sango).await?,
+        Command::Stats => stats(sango).await,
+        Command::Announce(text) => {
+            sango.client.request(CreateNote::new(text)).await?;
+            "投稿したよ".to_owned()
+        }
+        Command::Shutdown => "おやすみなさい……".to_owned(),
+    };
+    Ok(response)
+}
+
+async fn follow(acct: &str, follow: bool, sango: &Sango) -> anyhow::Result<String> {
+    let (username, host) = parse_acct(acct);
+    let result = match sango
+        .client
+        .request(ShowUser::by_username(username, host))
+        .await
+    {
+        Ok(user) if follow => sango.client.request(CreateFollowing::new(&user.id)).await,
+        Ok(user) => sango.client.request(DeleteFollowing::new(&user.id)).await,
+        Err(e) => Err(e),
+    };
+    let response = match result {
+        Ok(_) if follow => format!("{acct}をフォローしたよ"),
+        Ok(_) => format!("{acct}のフォローをやめたよ"),
+        Err(e) => match e.downcast_ref::<MisskeyError>() {
+            Some(MisskeyError::NoSuchUser) => format!("{acct}……？ 見つからなかったよ"),
+            Some(MisskeyError::AlreadyFollowing) => format!("{acct}はもうフォローしてるよ"),
+            Some(MisskeyError::NotFollowing) => format!("{acct}はもともとフォローしてないよ"),
+            Some(MisskeyError::Blocking | MisskeyError::Blocked) => {
+                format!("{acct}はフォローできないみたい……")
+            }
+            _ => return Err(e),
+        },
+    };
+    Ok(response)
+}
+
+async fn stats(sango: &Sango) -> String {
+    let now = Local::now();
+    let uptime = format_duration(now - sango.started_at);
+    let connection = sango.connection.read().await;
+    let connected = connection.connected_since.map_or_else(
+        || "切断中".to_owned(),
+        |since| format!("{}から", since.format("%m/%d %H:%M")),
+    );
+    let last_error = connection
+        .last_error
+        .as_deref()
+        .unwrap_or("なし")
+        .to_owned();
+    drop(connection);
+    let nicknames = sango
+        .savedata
+        .read()
+        .await
+        .nicknames()
+        .map_or(0, |nicknames| nicknames.len());
+    let timeline = if sango.muted.load(Ordering::Relaxed) {
+        "お休み中"
+    } else {
+        "反応中"
+    };
+    format!(
+        "起動してから{uptime}\n接続: {connected}\n最後のエラー: {last_error}\nタイムライン: {timeline}\n呼び名を覚えている人: {nicknames}人"
+    )
+}
+
+fn format_duration(duration: TimeDelta) -> String {
+    let days = duration.num_days();
+    let hours = duration.num_hours() % 24;
+    let minutes = duration.num_minutes() % 60;
+    if days > 0 {
+        format!("{days}日{hours}時間{minutes}分")
+    } else if hours > 0 {
+        format!("{hours}時間{minutes}分")
+    } else {
+        format!("{minutes}分")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        handler::mention::HandleMention,
+        misskey::fake::{self, ADMIN_ID, FakeClient, MODERATOR_ID},
+    };
+
+    #[test]
+    fn parse() {
+        assert!(matches!(
+            parse_command("@sango /reload"),
+            Some(Command::Reload)
+        ));
+        assert!(matches!(
+            parse_command("@sango@example.com /follow @alice@example.com"),
+            Some(Command::Follow("@alice@example.com"))
+        ));
+        assert!(matches!(
+            parse_command("@sango /announce おしらせ\nです"),
+            Some(Command::Announce("おしらせ\nです"))
+        ));
+        assert!(parse_command("@sango /follow").is_none());
+        assert!(parse_command("@sango /unknown").is_none());
+        assert!(parse_command("@sango こんにちは /reload").is_none());
+        assert_eq!(
+            parse_acct("@alice@example.com"),
+            ("alice", Some("example.com"))
+        );
+        assert_eq!(parse_acct("alice"), ("alice", None));
+    }
+
+    #[tokio::test]
+    async fn denied() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note("alice", "@sango /mute"), &sango)
+            .await;
+
+        assert!(!sango.muted.load(Ordering::Relaxed));
+        assert_eq!(client.notes(), [DENIED]);
+    }
+
+    #[tokio::test]
+    async fn moderator() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note(MODERATOR_ID, "@sango /mute"), &sango)
+            .await;
+        HandleMention
+            .handle(
+                &fake::note(MODERATOR_ID, "@sango /announce おしらせです"),
+                &sango,
+            )
+            .await;
+
+        assert!(sango.muted.load(Ordering::Relaxed));
+        assert_eq!(client.notes()[1], DENIED);
+    }
+
+    #[tokio::test]
+    async fn reload_roles() {
+        let client = FakeClient::default();
+        let mut sango = fake::sango(&client);
+        let dir = std::env::temp_dir().join(format!("sango_chan_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = format!(
+            "token = \"{}\"\nhost = \"localhost\"\nadmin = \"{ADMIN_ID}\"\n[roles]\nmoderator = [\"alice\"]",
+            crate::mock::TOKEN
+        );
+        std::fs::write(dir.join("config.toml"), config).unwrap();
+        sango.config_path = dir.join("config.toml");
+        sango.rules_path = dir.join("rules.toml");
+        HandleMention
+            .handle(&fake::note(ADMIN_ID, "@sango /reload"), &sango)
+            .await;
+        HandleMention
+            .handle(&fake::note("alice", "@sango /mute"), &sango)
+            .await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(client.notes()[0].contains("roles"));
+        assert!(client.notes()[0].contains("schedule"));
+        assert!(sango.muted.load(Ordering::Relaxed));
+        assert!(
+            !sango
+                .roles()
+                .has(&fake::note(MODERATOR_ID, "").user, Role::Moderator)
+        );
+    }
+
+    #[tokio::test]
+    async fn mute() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note(ADMIN_ID, "@sango /mute"), &sango)
+            .await;
+
+        assert!(sango.muted.load(Ordering::Relaxed));
+        assert!(sango.savedata.read().await.muted());
+    }
+
+    #[tokio::test]
+    async fn follow() {
+        let client = FakeClient::default();
+        client.relation(false, false);
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(
+                &fake::note(ADMIN_ID, "@sango /follow @alice@example.com"),
+                &sango,
+            )
+            .await;
+
+        assert_eq!(
+            client.requests::<ShowUser>(),
+            [ShowUser::by_username("alice", Some("example.com"))]
+        );
+        assert_eq!(
+            client.requests::<CreateFollowing>(),
+            [CreateFollowing::new("user")]
+        );
+    }
+
+    #[tokio::test]
+    async fn announce() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(
+                &fake::note(ADMIN_ID, "@sango /announce おしらせです"),
+                &sango,
+            )
+            .await;
+
+        assert_eq!(client.notes(), ["おしらせです", "投稿したよ"]);
+    }
+
+    #[tokio::test]
+    async fn specified_only() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let handler = HandleAdmin(AdminConfig {
+            specified_only: true,
+        });
+        handler
+            .handle(&fake::note(ADMIN_ID, "@sango /mute"), &sango)
+            .await;
+
+        assert!(!sango.muted.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn shutdown() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note(ADMIN_ID, "@sango /shutdown"), &sango)
+            .await;
+
+        let notified = tokio::time::timeout(Duration::from_secs(1), sango.shutdown.notified());
+        assert!(notified.await.is_ok());
+    }
+}
@@ -0,0 +1,69 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use crate::{
+    Sango,
+    handler::{UserHandler, registry::Registry},
+    misskey::{following::AcceptFollowRequest, users::User},
+};
+
+// 名前はconfig.tomlの[handlers.follow_request.<名前>]で使う
+pub fn register(registry: &mut Registry<User>) {
+    // 鍵アカウントにしているなら手で承認したいはずなので、初期状態では無効
+    registry.register("accept", 100, false, HandleAccept);
+}
+
+struct HandleAccept;
+impl UserHandler for HandleAccept {
+    async fn action(&self, user: &User, sango: &Sango) -> anyhow::Result<()> {
+        sango
+            .client
+            .request(AcceptFollowRequest::new(&user.id))
+            .await?;
+        log::info!("Accepted a follow request from {}.", user.id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        handler::registry::HandlerConfig,
+        misskey::fake::{self, FakeClient},
+        mock,
+    };
+
+    #[tokio::test]
+    async fn disabled_by_default() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let user = serde_json::from_value(mock::user("alice", "alice", false)).unwrap();
+        sango.handlers().follow_request.handle(&user, &sango).await;
+
+        assert!(client.requests::<AcceptFollowRequest>().is_empty());
+    }
+
+    #[tokio::test]
+    async fn accept() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let mut registry = Registry::default();
+        register(&mut registry);
+        let config = HandlerConfig {
+            enabled: Some(true),
+            ..Default::default()
+        };
+        registry.configure(&HashMap::from([("accept".to_owned(), config)]));
+        let user = serde_json::from_value(mock::user("alice", "alice", false)).unwrap();
+        registry.handle(&user, &sango).await;
+
+        assert_eq!(
+            client.requests::<AcceptFollowRequest>(),
+            [AcceptFollowRequest::new("alice")]
+        );
+    }
+}
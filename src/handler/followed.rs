@@ -4,21 +4,63 @@
 
 use crate::{
     Sango,
-    misskey::{notes::CreateNote, users::User},
+    handler::{UserHandler, registry::Registry},
+    misskey::users::User,
 };
 
-// TODO: なんかうまいことしてここもHandlerにしたい
-pub async fn on_follow(user: User, sango: &Sango) {
-    if user.is_bot || user.id == sango.self_id {
-        // BOTを無視
-        return;
+// 名前はconfig.tomlの[handlers.followed.<名前>]で使う
+pub fn register(registry: &mut Registry<User>) {
+    registry.register("welcome", 100, true, HandleWelcome);
+}
+
+struct HandleWelcome;
+impl UserHandler for HandleWelcome {
+    async fn respond(&self, user: &User, _sango: &Sango) -> anyhow::Result<String> {
+        let mention = user.mention();
+        Ok(format!(
+            "フォローありがとうございます、{mention}さん\n「フォローして」とメンションしながら投稿すると、フォローバックするよ"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        misskey::{
+            fake::{self, FakeClient},
+            users::User,
+        },
+        mock,
+    };
+
+    fn user(id: &str, is_bot: bool) -> User {
+        serde_json::from_value(mock::user(id, id, is_bot)).unwrap()
     }
 
-    let mention = user.mention();
-    let text = format!(
-        "フォローありがとうございます、{mention}さん\n「フォローして」とメンションしながら投稿すると、フォローバックするよ"
-    );
-    if let Err(e) = sango.client.request(CreateNote::new(&text)).await {
-        log::error!("{e}");
+    #[tokio::test]
+    async fn welcome() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        sango
+            .handlers()
+            .followed
+            .handle(&user("alice", false), &sango)
+            .await;
+
+        assert!(client.notes()[0].starts_with("フォローありがとうございます、@alice"));
+    }
+
+    #[tokio::test]
+    async fn ignores_bots() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let handled = sango
+            .handlers()
+            .followed
+            .handle(&user("bot", true), &sango)
+            .await;
+
+        assert!(!handled);
+        assert!(client.notes().is_empty());
     }
 }
@@ -10,12 +10,22 @@ use regex::Regex;
 
 use crate::{
     Sango,
-    handler::Handler,
+    handler::{
+        Handler,
+        admin::{AdminConfig, HandleAdmin},
+        registry::Registry,
+        reply,
+        rule::HandleRule,
+    },
     misskey::{
+        error::MisskeyError,
         following::{CreateFollowing, DeleteFollowing},
         notes::{CreateNote, Note},
         users::ShowUser,
     },
+    reminder::{self, Reminder},
+    roles::Role,
+    rules::Rule,
 };
 
 const MAX_NICKNAME_LENGTH: usize = 15;
@@ -28,35 +38,49 @@ impl Handler for HandleMention {
     }
 
     async fn action(&self, note: &Note, sango: &Sango) -> anyhow::Result<()> {
-        let _ = HandleFollow.handle(note, sango).await
-            || HandleUnFollow.handle(note, sango).await
-            || HandleAiScream1.handle(note, sango).await
-            || HandleAiScream2.handle(note, sango).await
-            || HandleSpeedtest.handle(note, sango).await
-            || HandleTodo.handle(note, sango).await
-            || HandleMeet.handle(note, sango).await
-            || HandleHello.handle(note, sango).await
-            || HandleIntro.handle(note, sango).await
-            || HandlePat.handle(note, sango).await
-            || HandleMeow.handle(note, sango).await
-            || HandleTime.handle(note, sango).await
-            || HandleInsult.handle(note, sango).await
-            || HandleChikuwa.handle(note, sango).await
-            || HandlePing.handle(note, sango).await
-            || HandleSetNickname.handle(note, sango).await
-            || HandleForgetNickname.handle(note, sango).await;
+        sango.handlers().mention.handle(note, sango).await;
         Ok(())
     }
 }
 
+// 名前はconfig.tomlの[handlers.mention.<名前>]で使う
+pub fn register(registry: &mut Registry<Note>, rules: Vec<Rule>, admin: AdminConfig) {
+    registry.register("admin", 110, true, HandleAdmin(admin));
+    registry.register("follow", 100, true, HandleFollow);
+    registry.register("unfollow", 90, true, HandleUnFollow);
+    registry.register("aiScream1", 80, true, HandleAiScream1);
+    registry.register("aiScream2", 80, true, HandleAiScream2);
+    registry.register("speedtest", 70, true, HandleSpeedtest);
+    registry.register("optOut", 65, true, HandleOptOut);
+    registry.register("optIn", 65, true, HandleOptIn);
+    registry.register("todo", 60, true, HandleTodo);
+    for rule in rules {
+        let name = rule.name().to_owned();
+        registry.register(&name, rule.priority, true, HandleRule(rule));
+    }
+    registry.register("time", 40, true, HandleTime);
+    registry.register("setNickname", 30, true, HandleSetNickname);
+    registry.register("forgetNickname", 20, true, HandleForgetNickname);
+    registry.register("conversation", 0, true, HandleConversation);
+}
+
 struct HandleFollow;
 impl Handler for HandleFollow {
     const KEYWORDS: &[&str] = &["フォローして"];
     async fn respond(&self, note: &Note, sango: &Sango) -> anyhow::Result<String> {
-        let user = sango
+        let user = match sango
             .client
             .request(ShowUser::by_user_id(&note.user_id))
-            .await?;
+            .await
+        {
+            Ok(user) => user,
+            Err(e) => {
+                return match e.downcast_ref::<MisskeyError>() {
+                    Some(MisskeyError::NoSuchUser) => Ok("……だれ？".to_owned()),
+                    _ => Err(e),
+                };
+            }
+        };
         let mention = note.user.mention();
 
         if user.is_following {
@@ -64,10 +88,21 @@ impl Handler for HandleFollow {
             Ok(format!("{mention} {name}さん、もうフォローしてるよー"))
         } else if user.is_followed {
             let name = note.user.name.as_ref().unwrap_or(&note.user.username);
-            sango
+            if let Err(e) = sango
                 .client
                 .request(CreateFollowing::new(&note.user_id))
-                .await?;
+                .await
+            {
+                return match e.downcast_ref::<MisskeyError>() {
+                    Some(MisskeyError::AlreadyFollowing) => {
+                        Ok(format!("{mention} {name}さん、もうフォローしてるよー"))
+                    }
+                    Some(MisskeyError::Blocking | MisskeyError::Blocked) => Ok(format!(
+                        "{mention} ごめんね、いまはフォローできないみたい……"
+                    )),
+                    _ => Err(e),
+                };
+            }
             log::info!("Followed {}.", note.user_id);
             Ok(format!(
                 "{mention} フォローバックしたよ、{name}さん。これからよろしくね",
@@ -90,15 +125,21 @@ impl Handler for HandleUnFollow {
         let mention = note.user.mention();
         if user.is_following {
             let response = format!("{mention} さよなら、になっちゃうのかな……");
-            sango.client.request(note.reply(&response)).await?;
+            reply(note, &response, sango).await?;
             tokio::time::sleep(Duration::from_secs(10)).await;
-            sango
+            if let Err(e) = sango
                 .client
                 .request(DeleteFollowing::new(&note.user_id))
-                .await?;
+                .await
+            {
+                // 待っている間に解除済みなら何もしない
+                if !matches!(e.downcast_ref(), Some(MisskeyError::NotFollowing)) {
+                    return Err(e);
+                }
+            }
         } else {
             let response = format!("{mention} もともとフォローしてないよー");
-            sango.client.request(note.reply(&response)).await?;
+            reply(note, &response, sango).await?;
         }
         Ok(())
     }
@@ -118,10 +159,7 @@ impl Handler for HandleAiScream2 {
     const KEYWORDS: &[&str] = &["何が好き？"];
     async fn action(&self, note: &Note, sango: &Sango) -> anyhow::Result<()> {
         tokio::time::sleep(Duration::from_secs(1)).await;
-        sango
-            .client
-            .request(note.reply("チョココーヒー よりもあ・な・た♪"))
-            .await?;
+        reply(note, "チョココーヒー よりもあ・な・た♪", sango).await?;
         tokio::time::sleep(Duration::from_secs(10)).await;
         sango
             .client
@@ -134,15 +172,9 @@ impl Handler for HandleAiScream2 {
 struct HandleSpeedtest;
 impl Handler for HandleSpeedtest {
     const KEYWORDS: &[&str] = &["回線速度計測"];
+    const ROLE: Role = Role::Trusted;
     async fn respond(&self, note: &Note, sango: &Sango) -> anyhow::Result<String> {
-        if note.user_id != sango.admin_id {
-            return Ok("この機能は使える人が限られてるんだ。ゴメンね".to_owned());
-        }
-
-        sango
-            .client
-            .request(note.reply("了解。じゃあ計測してくるね"))
-            .await?;
+        reply(note, "了解。じゃあ計測してくるね", sango).await?;
 
         log::info!("Starting speedtest...");
         let (ping, down, up) = tokio::task::spawn_blocking(speedtest).await?;
@@ -171,44 +203,24 @@ fn speedtest() -> (f64, f64, f64) {
 struct HandleTodo;
 impl Handler for HandleTodo {
     const KEYWORDS: &[&str] = &["todo"];
-    async fn respond(&self, _note: &Note, _sango: &Sango) -> anyhow::Result<String> {
+    async fn respond(&self, note: &Note, sango: &Sango) -> anyhow::Result<String> {
+        let now = Local::now();
+        let due =
+            reminder::parse_due(&note.text, now).unwrap_or_else(|| reminder::default_due(now));
+        sango.savedata.write().await.add_reminder(&Reminder {
+            note_id: note.id.clone(),
+            user_id: note.user_id.clone(),
+            visibility: note.visibility,
+            due,
+            chat: note.chat,
+        })?;
+        sango.reminder_notify.notify_one();
         log::info!("Todo created.");
-        tokio::time::sleep(Duration::from_secs(60 * 60 * 3)).await;
-        Ok("これやった？".to_owned())
+        let due = reminder::format_due(due, now);
+        Ok(format!("わかった。{due}になったら聞くね"))
     }
 }
 
-struct HandleMeet;
-impl Handler for HandleMeet {
-    const KEYWORDS: &[&str] = &["はじめまして"];
-    const RESPONSE: &str = "はじめまして、わたしを見つけてくれてありがとう。これからよろしくね";
-}
-
-struct HandleHello;
-impl Handler for HandleHello {
-    const KEYWORDS: &[&str] = &["こんにちは"];
-    const RESPONSE: &str = "こんにちは、どうしたの？";
-}
-
-struct HandleIntro;
-impl Handler for HandleIntro {
-    const KEYWORDS: &[&str] = &["自己紹介", "あなたは？"];
-    const RESPONSE: &str = "わたしは「3.5Mbps.net」の看板娘、さんご……のクローンです。……めんどうだから、わたしのことも「さんご」でいいよ。\nあなたのことも、教えて欲しいな";
-}
-
-struct HandlePat;
-impl Handler for HandlePat {
-    const KEYWORDS: &[&str] = &["よしよし", "なでなで"];
-    const RESPONSE: &str =
-        "わたしの頭なんか撫でて、楽しい？ えっと、あなたが喜んでくれるなら、いいんだけど……";
-}
-
-struct HandleMeow;
-impl Handler for HandleMeow {
-    const KEYWORDS: &[&str] = &["にゃーん"];
-    const RESPONSE: &str = "にゃ〜ん";
-}
-
 struct HandleTime;
 impl Handler for HandleTime {
     const KEYWORDS: &[&str] = &["今何時", "いまなんじ"];
@@ -223,32 +235,6 @@ impl Handler for HandleTime {
     }
 }
 
-struct HandleInsult;
-impl Handler for HandleInsult {
-    const KEYWORDS: &[&str] = &["罵って"];
-    async fn respond(&self, _note: &Note, _sango: &Sango) -> anyhow::Result<String> {
-        let chosen = [
-            "変なお願いをするもんだね……",
-            "えっと……、ど、どんな風に罵ってほしいとか、ある？",
-        ]
-        .choose(&mut rand::rng())
-        .unwrap();
-        Ok(chosen.to_owned().to_owned())
-    }
-}
-
-struct HandleChikuwa;
-impl Handler for HandleChikuwa {
-    const KEYWORDS: &[&str] = &["ちくわ大明神"];
-    const RESPONSE: &str = "…なに？";
-}
-
-struct HandlePing;
-impl Handler for HandlePing {
-    const KEYWORDS: &[&str] = &["ping"];
-    const RESPONSE: &str = "pong？";
-}
-
 struct HandleSetNickname;
 impl Handler for HandleSetNickname {
     const KEYWORDS: &[&str] = &["って呼んで", "と呼んで"];
@@ -348,3 +334,208 @@ impl Handler for HandleForgetNickname {
         }
     }
 }
+
+// BOTのノートへのリプライで、ほかのどれにも当てはまらなかったときの相づち
+struct HandleConversation;
+impl Handler for HandleConversation {
+    fn gate(&self, note: &Note, sango: &Sango) -> bool {
+        note.is_reply_to(&sango.self_id)
+    }
+
+    async fn respond(&self, _note: &Note, _sango: &Sango) -> anyhow::Result<String> {
+        const RESPONSES: &[&str] = &[
+            "うん、うん",
+            "そうなんだ……",
+            "なるほどね",
+            "ふふ、そっか",
+            "えっと……、もうちょっと詳しく聞かせて？",
+        ];
+        let response = RESPONSES.choose(&mut rand::rng()).unwrap_or(&"うん");
+        Ok((*response).to_owned())
+    }
+}
+
+// タイムラインへの反応だけ止める。メンションには今まで通り返事する
+struct HandleOptOut;
+impl Handler for HandleOptOut {
+    const KEYWORDS: &[&str] = &["反応しないで"];
+    async fn respond(&self, note: &Note, sango: &Sango) -> anyhow::Result<String> {
+        set_opted_out(&note.user_id, true, sango).await?;
+        Ok("わかった。これからはタイムラインの投稿には反応しないでおくね\nまた反応してほしくなったら「また反応して」ってメンションしてね".to_owned())
+    }
+}
+
+struct HandleOptIn;
+impl Handler for HandleOptIn {
+    const KEYWORDS: &[&str] = &["また反応して"];
+    async fn respond(&self, note: &Note, sango: &Sango) -> anyhow::Result<String> {
+        set_opted_out(&note.user_id, false, sango).await?;
+        Ok("うん、またタイムラインの投稿にも反応するね".to_owned())
+    }
+}
+
+async fn set_opted_out(user_id: &str, opted_out: bool, sango: &Sango) -> anyhow::Result<()> {
+    // 同時に来ても、保存される順番とメモリ上の順番が食い違わないように、先に保存先を押さえる
+    let mut savedata = sango.savedata.write().await;
+    let mut users = sango.opted_out.lock().unwrap();
+    if opted_out {
+        users.insert(user_id.to_owned());
+    } else {
+        users.remove(user_id);
+    }
+    let saved = savedata.set_opted_out(&users);
+    drop(users);
+    drop(savedata);
+    saved
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use super::*;
+    use crate::misskey::fake::{self, FakeClient};
+
+    #[tokio::test]
+    async fn conversation() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let note = fake::note("alice", "@sango 今日はいい天気だね");
+        HandleMention.handle(&note, &sango).await;
+        assert!(client.notes().is_empty());
+
+        let mut reply = fake::note("alice", "今日はいい天気だね");
+        reply.reply_to = Some(Box::new(fake::note(&sango.self_id, "おはよう")));
+        HandleMention.handle(&reply, &sango).await;
+        assert_eq!(client.notes().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn follow_back() {
+        let client = FakeClient::default();
+        client.relation(false, true);
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note("alice", "@sango フォローして"), &sango)
+            .await;
+
+        assert_eq!(
+            client.requests::<CreateFollowing>(),
+            [CreateFollowing::new("alice")]
+        );
+        assert!(client.notes()[0].contains("フォローバックしたよ"));
+    }
+
+    #[tokio::test]
+    async fn follow_back_stranger() {
+        let client = FakeClient::default();
+        client.relation(false, false);
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note("alice", "@sango フォローして"), &sango)
+            .await;
+
+        assert!(client.requests::<CreateFollowing>().is_empty());
+        assert_eq!(client.notes(), ["……だれ？"]);
+    }
+
+    #[tokio::test]
+    async fn follow_back_already_following() {
+        let client = FakeClient::default();
+        client.relation(false, true);
+        client.fail::<CreateFollowing>(StatusCode::BAD_REQUEST, "ALREADY_FOLLOWING");
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note("alice", "@sango フォローして"), &sango)
+            .await;
+
+        assert!(client.notes()[0].contains("もうフォローしてるよー"));
+    }
+
+    #[tokio::test]
+    async fn follow_back_no_such_user() {
+        let client = FakeClient::default();
+        client.fail::<ShowUser>(StatusCode::BAD_REQUEST, "NO_SUCH_USER");
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note("alice", "@sango フォローして"), &sango)
+            .await;
+
+        assert_eq!(client.notes(), ["……だれ？"]);
+    }
+
+    #[tokio::test]
+    async fn set_nickname() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note("alice", "@sango ねこって呼んで"), &sango)
+            .await;
+
+        let nickname = sango.savedata.read().await.get_nickname("alice");
+        assert_eq!(nickname.as_deref(), Some("ねこ"));
+        assert!(client.notes()[0].contains("ねこさん"));
+    }
+
+    #[tokio::test]
+    async fn set_nickname_too_long() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(
+                &fake::note(
+                    "alice",
+                    "@sango じゅげむじゅげむごこうのすりきれかいじゃりすいぎょって呼んで",
+                ),
+                &sango,
+            )
+            .await;
+
+        assert!(sango.savedata.read().await.get_nickname("alice").is_none());
+        assert!(client.notes()[0].contains("長いかも"));
+    }
+
+    #[tokio::test]
+    async fn forget_nickname() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        sango
+            .savedata
+            .write()
+            .await
+            .store_nickname("alice", "ねこ")
+            .unwrap();
+        HandleMention
+            .handle(&fake::note("alice", "@sango 呼び名を忘れて"), &sango)
+            .await;
+
+        assert!(sango.savedata.read().await.get_nickname("alice").is_none());
+    }
+
+    #[tokio::test]
+    async fn speedtest_needs_trusted() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note("alice", "@sango 回線速度計測"), &sango)
+            .await;
+
+        assert_eq!(
+            client.notes(),
+            ["この機能は使える人が限られてるんだ。ゴメンね"]
+        );
+    }
+
+    #[tokio::test]
+    async fn todo() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note("alice", "@sango todo 30分後"), &sango)
+            .await;
+
+        let reminder = sango.savedata.read().await.next_reminder().unwrap();
+        assert_eq!(reminder.user_id, "alice");
+        assert!(client.notes()[0].starts_with("わかった。"));
+    }
+}
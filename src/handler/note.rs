@@ -2,81 +2,63 @@
 //
 // SPDX-License-Identifier: UPL-1.0
 
-use rand::seq::IndexedRandom;
+use std::sync::atomic::Ordering;
 
-use crate::{Sango, handler::Handler, misskey::notes::Note};
+use chrono::Local;
 
-pub struct HandleNote;
+use crate::{
+    Sango,
+    handler::{Handler, registry::Registry, rule::HandleRule},
+    misskey::notes::Note,
+    rules::Rule,
+};
+
+// 中身はどのチャンネルから来たか(config.tomlの[[channels]]の何番目か)
+pub struct HandleNote(pub usize);
 impl Handler for HandleNote {
     fn gate(&self, note: &Note, sango: &Sango) -> bool {
         !note.user.is_bot // BOTを無視
         && note.user.id != sango.self_id // 自身を無視
         && !note.mentions.contains(&sango.self_id) // メンションはEventBodyType::Mentionで処理するので無視
+        && !sango.muted.load(Ordering::Relaxed) // 管理者に止められているときは無視
+        && !sango.opted_out.lock().unwrap().contains(&note.user_id) // 反応しないでと言われた人は無視
+        && sango.budget.allows(Local::now()) // 静かにする時間や、反応しすぎのときは無視
     }
 
     async fn action(&self, note: &Note, sango: &Sango) -> anyhow::Result<()> {
-        let _ = HandlePain.handle(note, sango).await
-            || HandleTired.handle(note, sango).await
-            || HandleGoWork.handle(note, sango).await
-            || HandleLeaveWork.handle(note, sango).await
-            || HandleNullpo.handle(note, sango).await
-            || HandleCall.handle(note, sango).await
-            || HandleSleepy.handle(note, sango).await
-            || HandleGoodMorning.handle(note, sango).await
-            || HandleGoodNight.handle(note, sango).await
-            || HandleLateMorning.handle(note, sango).await
-            || HandleMeow.handle(note, sango).await
-            || HandleSleepAgain.handle(note, sango).await;
+        let handlers = sango.handlers();
+        // 読み込み直してチャンネルが減っていたら何もしない
+        let Some(timeline) = handlers.timelines.get(self.0) else {
+            return Ok(());
+        };
+        let Some(entry) = timeline.find(note, sango) else {
+            return Ok(());
+        };
+        // 別のチャンネルで同じ投稿に反応済みなら何もしない
+        if !sango.recent_notes.insert(&note.id) {
+            return Ok(());
+        }
+        let now = Local::now();
+        if !sango.cooldowns.try_start(&note.user_id, entry.name(), now) {
+            log::debug!("Skipping {} for {} (cooldown).", entry.name(), note.user_id);
+            return Ok(());
+        }
+        sango.budget.spend(now);
+        if let Some(snapshot) = sango.cooldowns.snapshot_to_save(now) {
+            sango.savedata.write().await.set_cooldowns(&snapshot)?;
+        }
+        entry.run(note, sango).await;
         Ok(())
     }
 }
 
-struct HandlePain;
-impl Handler for HandlePain {
-    const KEYWORDS: &[&str] = &["つらい", "つらすぎ"];
-    const RESPONSE: &str = "つらいときは、甘えてもいいんだよ？";
-}
-
-struct HandleTired;
-impl Handler for HandleTired {
-    const KEYWORDS: &[&str] = &[
-        "疲れた",
-        "つかれた",
-        "疲れてる",
-        "つかれてる",
-        "疲れている",
-        "つかれている",
-    ];
-    const RESPONSE: &str = "ひとやすみ、する？ それとも、わたしが癒してあげよっか？";
-}
-
-struct HandleGoWork;
-impl Handler for HandleGoWork {
-    const KEYWORDS: &[&str] = &["出勤"];
-    async fn respond(&self, _note: &Note, _sango: &Sango) -> anyhow::Result<String> {
-        let response = [
-            "お仕事、頑張ってきてね。わたし、帰ってくるの、待ってるから……",
-            "お仕事は大事だけど、あんまり無理はしないでね？",
-            "お仕事とわたし、どっちが大事なんだろう……。まぁ、わたしにはロイちゃんがいるから、いい……のかな？\n……あっ、ち、違う！ これは違くて…！ なんでもないから……！",
-        ].choose(&mut rand::rng()).unwrap();
-        Ok(response.to_owned().to_owned())
+// 名前はconfig.tomlの[handlers.note.<名前>]で使う
+pub fn register(registry: &mut Registry<Note>, rules: Vec<Rule>) {
+    for rule in rules {
+        let name = rule.name().to_owned();
+        registry.register(&name, rule.priority, true, HandleRule(rule));
     }
-}
-
-struct HandleLeaveWork;
-impl Handler for HandleLeaveWork {
-    const KEYWORDS: &[&str] = &["退勤"];
-    const RESPONSE: &str =
-        "お仕事終わったの？ お疲れさま～。 ……わたしの癒し、必要かな？ 必要なら、いつでも言ってね";
-}
-
-struct HandleNullpo;
-impl Handler for HandleNullpo {
-    const KEYWORDS: &[&str] = &["ぬるぽ"];
-    fn cond(&self, _note: &Note) -> bool {
-        rand::random_bool(1.0 / 3.0)
-    }
-    const RESPONSE: &str = "ガッ";
+    registry.register("call", 0, true, HandleCall);
 }
 
 struct HandleCall;
@@ -92,85 +74,126 @@ impl Handler for HandleCall {
     }
 }
 
-struct HandleSleepy;
-impl Handler for HandleSleepy {
-    const KEYWORDS: &[&str] = &["眠い", "眠たい", "ねむ"];
-    fn cond(&self, note: &Note) -> bool {
-        if note.text.contains("ねむ") {
-            note.reply_id.is_none() && !note.text.contains("くない")
-        } else {
-            note.reply_id.is_none()
-        }
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        budget::{Budget, BudgetConfig},
+        handler::{Handlers, mention::HandleMention},
+        misskey::fake::{self, FakeClient},
+        rules::{self, Rules},
+        websocket::{Channel, ChannelConfig},
+    };
+
+    #[tokio::test]
+    async fn reacts_to_keyword() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleNote(0)
+            .handle(&fake::note("alice", "つかれた"), &sango)
+            .await;
+
+        assert_eq!(client.notes().len(), 1);
     }
-    const RESPONSE: &str = "なるほど、眠いんだね。……我慢はよくないよ？ 欲には素直にならないと";
-}
 
-struct HandleGoodMorning;
-impl Handler for HandleGoodMorning {
-    const KEYWORDS: &[&str] = &["おはよ"];
-    fn cond(&self, note: &Note) -> bool {
-        note.reply_id.is_none()
+    #[tokio::test]
+    async fn opt_out() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleMention
+            .handle(&fake::note("alice", "@sango 反応しないで"), &sango)
+            .await;
+        HandleNote(0)
+            .handle(&fake::note("alice", "つかれた"), &sango)
+            .await;
+        assert_eq!(client.notes().len(), 1);
+        assert!(sango.savedata.read().await.opted_out().contains("alice"));
+
+        HandleMention
+            .handle(&fake::note("alice", "@sango また反応して"), &sango)
+            .await;
+        HandleNote(0)
+            .handle(&fake::note("alice", "つかれた"), &sango)
+            .await;
+        assert_eq!(client.notes().len(), 3);
     }
-    async fn respond(&self, _note: &Note, _sango: &Sango) -> anyhow::Result<String> {
-        let state = [
-            "よく眠れたよ～。元気いーっぱい",
-            "あんまり寝れなかったかな……。まぁ、なんとかなるでしょ～",
-        ]
-        .choose(&mut rand::rng())
-        .unwrap();
-        Ok(format!("おはよ、よく眠れた？ わたしは{state}"))
-    }
-}
 
-struct HandleGoodNight;
-impl Handler for HandleGoodNight {
-    const KEYWORDS: &[&str] = &["おやすみ"];
-    fn cond(&self, note: &Note) -> bool {
-        note.reply_id.is_none() && !note.text.contains("すきー")
-    }
-    async fn respond(&self, _note: &Note, _sango: &Sango) -> anyhow::Result<String> {
-        let response = [
-            "また朝に会おうね、おやすみ",
-            "おやすみって言ったんだから、夜更かししようなんて考えないでね？",
-            "寝ちゃうんだ……。ふーん……",
-        ]
-        .choose(&mut rand::rng())
-        .unwrap();
-        Ok(response.to_owned().to_owned())
+    #[tokio::test]
+    async fn ignores_mentions() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleNote(0)
+            .handle(&fake::note("alice", "@sango つかれた"), &sango)
+            .await;
+
+        assert!(client.notes().is_empty());
     }
-}
 
-struct HandleLateMorning;
-impl Handler for HandleLateMorning {
-    const KEYWORDS: &[&str] = &["おそよ"];
-    fn cond(&self, note: &Note) -> bool {
-        note.reply_id.is_none()
+    #[tokio::test]
+    async fn ignores_self() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        HandleNote(0)
+            .handle(&fake::note(&sango.self_id, "つかれた"), &sango)
+            .await;
+
+        assert!(client.notes().is_empty());
     }
-    const RESPONSE: &str = "遅いよ、ねぼすけさん。なんで寝坊したのか、ちゃんと説明して？";
-}
 
-struct HandleMeow;
-impl Handler for HandleMeow {
-    const KEYWORDS: &[&str] = &["にゃーん"];
-    fn cond(&self, note: &Note) -> bool {
-        note.reply_id.is_none() && rand::random_bool(1.0 / 2.0)
+    #[tokio::test]
+    async fn cooldown() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        for (i, user) in ["alice", "alice", "bob"].into_iter().enumerate() {
+            let mut note = fake::note(user, "つかれた");
+            note.id = format!("note{i}");
+            HandleNote(0).handle(&note, &sango).await;
+        }
+
+        assert_eq!(client.notes().len(), 2);
     }
-    const RESPONSE: &str = "にゃーん。……えへへ、わたしも混ぜて？";
-}
 
-struct HandleSleepAgain;
-impl Handler for HandleSleepAgain {
-    const KEYWORDS: &[&str] = &["二度寝"];
-    fn cond(&self, note: &Note) -> bool {
-        note.reply_id.is_none()
+    #[tokio::test]
+    async fn channels() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let mut config = fake::config();
+        config.channels.push(ChannelConfig {
+            channel: Channel::LocalTimeline,
+            handlers: Some(vec!["call".to_owned()]),
+        });
+        *sango.handlers.write().unwrap() = Arc::new(Handlers::new(
+            Rules::parse(rules::DEFAULT_RULES).unwrap(),
+            &config,
+        ));
+        let note = fake::note("alice", "つかれた");
+
+        // 2番目のチャンネルでは"call"にしか反応しない
+        HandleNote(1).handle(&note, &sango).await;
+        assert!(client.notes().is_empty());
+
+        // 同じ投稿が別のチャンネルから来ても1回だけ
+        HandleNote(0).handle(&note, &sango).await;
+        HandleNote(0).handle(&note, &sango).await;
+        assert_eq!(client.notes().len(), 1);
     }
-    async fn respond(&self, _note: &Note, _sango: &Sango) -> anyhow::Result<String> {
-        let response = [
-            "二度寝をするのは悪いことではないけど、ほどほどにしておいてね？",
-            "30分後にアラームを設定。……よし、準備おっけー。じゃあ、わたしも二度寝しちゃおうかな……",
-        ]
-        .choose(&mut rand::rng())
-        .unwrap();
-        Ok(response.to_owned().to_owned())
+
+    #[tokio::test]
+    async fn budget() {
+        let client = FakeClient::default();
+        let mut sango = fake::sango(&client);
+        sango.budget = Budget::new(BudgetConfig {
+            per_hour: 1,
+            ..Default::default()
+        });
+        for user in ["alice", "bob"] {
+            HandleNote(0)
+                .handle(&fake::note(user, "つかれた"), &sango)
+                .await;
+        }
+
+        assert_eq!(client.notes().len(), 1);
     }
 }
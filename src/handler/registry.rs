@@ -0,0 +1,229 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::collections::HashMap;
+
+use futures::{FutureExt, future::BoxFuture};
+use serde::Deserialize;
+
+use crate::{
+    Sango,
+    handler::{self, Handler, UserHandler},
+    misskey::{notes::Note, users::User},
+};
+
+// config.tomlの[handlers.<イベント>.<名前>]
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct HandlerConfig {
+    pub enabled: Option<bool>,
+    pub priority: Option<i32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct HandlersConfig {
+    pub mention: HashMap<String, HandlerConfig>,
+    pub note: HashMap<String, HandlerConfig>,
+    pub followed: HashMap<String, HandlerConfig>,
+    pub follow_request: HashMap<String, HandlerConfig>,
+    pub unfollow: HashMap<String, HandlerConfig>,
+    pub renote: HashMap<String, HandlerConfig>,
+    pub quote: HashMap<String, HandlerConfig>,
+}
+
+// 種類の違うHandlerを同じVecに入れるためのもの。TはNoteかUser
+// 反応するか決めてから実際に動くまでの間に、クールダウンなどを挟めるように分けてある
+pub trait DynHandler<T>: Send + Sync {
+    fn gate(&self, target: &T, sango: &Sango) -> bool;
+    fn action<'a>(&'a self, target: &'a T, sango: &'a Sango) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+impl<H: Handler + Send> DynHandler<Note> for H {
+    fn gate(&self, note: &Note, sango: &Sango) -> bool {
+        Handler::gate(self, note, sango)
+    }
+    fn action<'a>(&'a self, note: &'a Note, sango: &'a Sango) -> BoxFuture<'a, anyhow::Result<()>> {
+        handler::run(self, note, sango).boxed()
+    }
+}
+
+impl<H: UserHandler + Send> DynHandler<User> for H {
+    fn gate(&self, user: &User, sango: &Sango) -> bool {
+        UserHandler::gate(self, user, sango)
+    }
+    fn action<'a>(&'a self, user: &'a User, sango: &'a Sango) -> BoxFuture<'a, anyhow::Result<()>> {
+        UserHandler::action(self, user, sango).boxed()
+    }
+}
+
+pub struct Entry<T> {
+    name: String,
+    priority: i32,
+    enabled: bool,
+    handler: Box<dyn DynHandler<T>>,
+}
+
+// priorityの大きいものから順に試して、最初に反応したもので終わり
+pub struct Registry<T>(Vec<Entry<T>>);
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T: Sync> Registry<T> {
+    pub fn register<H: DynHandler<T> + 'static>(
+        &mut self,
+        name: &str,
+        priority: i32,
+        enabled: bool,
+        handler: H,
+    ) {
+        if self.0.iter().any(|entry| entry.name == name) {
+            log::warn!("Handler {name} is registered twice; The latter is ignored.");
+            return;
+        }
+        self.0.push(Entry {
+            name: name.to_owned(),
+            priority,
+            enabled,
+            handler: Box::new(handler),
+        });
+    }
+
+    // 設定を反映して並べ直す。同じpriorityなら登録した順
+    pub fn configure(&mut self, config: &HashMap<String, HandlerConfig>) {
+        for (name, config) in config {
+            let Some(entry) = self.0.iter_mut().find(|entry| &entry.name == name) else {
+                log::warn!("Unknown handler in config: {name}");
+                continue;
+            };
+            entry.enabled = config.enabled.unwrap_or(entry.enabled);
+            entry.priority = config.priority.unwrap_or(entry.priority);
+        }
+        self.0
+            .sort_by_key(|entry| std::cmp::Reverse(entry.priority));
+    }
+
+    // namesにあるものだけ残す
+    pub fn retain(&mut self, names: &[String]) {
+        for name in names {
+            if !self.0.iter().any(|entry| &entry.name == name) {
+                log::warn!("Unknown handler in channels: {name}");
+            }
+        }
+        self.0.retain(|entry| names.contains(&entry.name));
+    }
+
+    pub fn has_enabled(&self) -> bool {
+        self.0.iter().any(|entry| entry.enabled)
+    }
+
+    // 試す順の名前
+    #[cfg(test)]
+    pub fn names(&self) -> Vec<&str> {
+        self.0.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    // 反応するものを探すだけで、まだ動かさない
+    pub fn find(&self, target: &T, sango: &Sango) -> Option<&Entry<T>> {
+        self.0
+            .iter()
+            .filter(|entry| entry.enabled)
+            .find(|entry| entry.handler.gate(target, sango))
+    }
+
+    pub async fn handle(&self, target: &T, sango: &Sango) -> bool {
+        let Some(entry) = self.find(target, sango) else {
+            return false;
+        };
+        entry.run(target, sango).await;
+        true
+    }
+}
+
+impl<T: Sync> Entry<T> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub async fn run(&self, target: &T, sango: &Sango) {
+        log::debug!("Handled by {}.", self.name);
+        if let Err(e) = self.handler.action(target, sango).await {
+            log::error!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::misskey::fake::{self, FakeClient};
+
+    struct First;
+    impl Handler for First {
+        const KEYWORDS: &[&str] = &["テスト"];
+        const RESPONSE: &str = "first";
+    }
+
+    struct Second;
+    impl Handler for Second {
+        const KEYWORDS: &[&str] = &["テスト"];
+        const RESPONSE: &str = "second";
+    }
+
+    fn registry(config: &[(&str, HandlerConfig)]) -> Registry<Note> {
+        let mut registry = Registry::default();
+        registry.register("first", 10, true, First);
+        registry.register("second", 5, true, Second);
+        let config = config
+            .iter()
+            .map(|(name, config)| ((*name).to_owned(), *config))
+            .collect();
+        registry.configure(&config);
+        registry
+    }
+
+    #[tokio::test]
+    async fn priority() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let note = fake::note("alice", "テスト");
+
+        registry(&[]).handle(&note, &sango).await;
+        let config = HandlerConfig {
+            priority: Some(20),
+            ..Default::default()
+        };
+        registry(&[("second", config)]).handle(&note, &sango).await;
+
+        assert_eq!(client.notes(), ["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn disabled() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let note = fake::note("alice", "テスト");
+
+        let config = HandlerConfig {
+            enabled: Some(false),
+            ..Default::default()
+        };
+        let handled = registry(&[("first", config)]).handle(&note, &sango).await;
+        let config = HandlerConfig {
+            enabled: Some(false),
+            ..Default::default()
+        };
+        let all_disabled = registry(&[("first", config), ("second", config)])
+            .handle(&note, &sango)
+            .await;
+
+        assert!(handled);
+        assert!(!all_disabled);
+        assert_eq!(client.notes(), ["second"]);
+    }
+}
@@ -0,0 +1,94 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+// BOTのノートがリノートされたときの反応
+// 本文のないリノートにはリアクションも返信もできないので、普通のノートでお礼を言う
+
+use crate::{
+    Sango,
+    handler::{Handler, registry::Registry},
+    misskey::notes::{CreateNote, Note},
+};
+
+// 名前はconfig.tomlの[handlers.renote.<名前>]と[handlers.quote.<名前>]で使う
+pub fn register(renote: &mut Registry<Note>, quote: &mut Registry<Note>) {
+    renote.register("thanks", 100, false, HandleRenoteThanks);
+    quote.register("thanks", 100, true, HandleQuoteThanks);
+}
+
+fn is_person(note: &Note, sango: &Sango) -> bool {
+    !note.user.is_bot // BOTを無視
+    && note.user_id != sango.self_id // 自身を無視
+}
+
+struct HandleRenoteThanks;
+impl Handler for HandleRenoteThanks {
+    fn gate(&self, note: &Note, sango: &Sango) -> bool {
+        is_person(note, sango)
+    }
+
+    async fn action(&self, note: &Note, sango: &Sango) -> anyhow::Result<()> {
+        let mention = note.user.mention();
+        sango
+            .client
+            .request(CreateNote::new(&format!(
+                "リノートありがとう、{mention}さん"
+            )))
+            .await?;
+        Ok(())
+    }
+}
+
+struct HandleQuoteThanks;
+impl Handler for HandleQuoteThanks {
+    const REACTION: &str = "🙏";
+    fn gate(&self, note: &Note, sango: &Sango) -> bool {
+        is_person(note, sango)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        misskey::{
+            fake::{self, FakeClient},
+            notes::{CreateReaction, Note},
+        },
+        mock,
+    };
+
+    fn renote(text: Option<&str>) -> Note {
+        let user = mock::user("alice", "alice", false);
+        let mut note = mock::note("renote", &user, "");
+        note["text"] = text.into();
+        note["renoteId"] = "sango_note".into();
+        serde_json::from_value(note).unwrap()
+    }
+
+    #[tokio::test]
+    async fn renote_disabled_by_default() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let note = renote(None);
+        let handled = sango.handlers().renote.handle(&note, &sango).await;
+
+        assert!(!note.is_quote());
+        assert!(!handled);
+        assert!(client.notes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn quote() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let note = renote(Some("これ見て"));
+        sango.handlers().quote.handle(&note, &sango).await;
+
+        assert!(note.is_quote());
+        assert_eq!(
+            client.requests::<CreateReaction>(),
+            [CreateReaction::new("renote", "🙏")]
+        );
+    }
+}
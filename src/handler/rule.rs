@@ -0,0 +1,135 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use rand::seq::IndexedRandom;
+
+use crate::{
+    Sango,
+    handler::Handler,
+    misskey::notes::Note,
+    rules::{ReplyCondition, Rule},
+};
+
+// 返信中の相手の呼び名に置き換える部分
+#[allow(clippy::literal_string_with_formatting_args)]
+const NAME_PLACEHOLDER: &str = "{name}";
+
+// rules.tomlのルールをHandlerとして動かす
+pub struct HandleRule(pub Rule);
+impl Handler for HandleRule {
+    fn cond(&self, note: &Note) -> bool {
+        let rule = &self.0;
+        let reply_check = match rule.reply {
+            ReplyCondition::Any => true,
+            ReplyCondition::Only => note.reply_id.is_some(),
+            ReplyCondition::Never => note.reply_id.is_none(),
+        };
+        let pattern_check = rule
+            .exclude_pattern
+            .as_ref()
+            .is_none_or(|pattern| !pattern.is_match(&note.text));
+        reply_check
+            && !rule.exclude.iter().any(|word| note.text.contains(word))
+            && pattern_check
+            && rand::random_bool(rule.probability)
+    }
+
+    fn gate(&self, note: &Note, _sango: &Sango) -> bool {
+        let keyword_check = self
+            .0
+            .keywords
+            .iter()
+            .any(|keyword| note.text.contains(keyword));
+        keyword_check && self.cond(note)
+    }
+
+    fn reaction(&self, _note: &Note) -> String {
+        self.0.reaction.clone().unwrap_or_default()
+    }
+
+    async fn respond(&self, note: &Note, sango: &Sango) -> anyhow::Result<String> {
+        let Some(response) = self.0.responses.choose(&mut rand::rng()) else {
+            return Ok(String::new());
+        };
+        if response.contains(NAME_PLACEHOLDER) {
+            let name = sango.savedata.read().await.get_displayname(&note.user);
+            Ok(response.replace(NAME_PLACEHOLDER, &name))
+        } else {
+            Ok(response.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use super::*;
+    use crate::{
+        misskey::{
+            fake::{self, FakeClient},
+            notes::{CreateReaction, DeleteReaction},
+        },
+        rules::Rules,
+    };
+
+    fn rule(toml: &str) -> HandleRule {
+        let rules = Rules::parse(&format!("[[note]]\nkeywords = [\"にゃーん\"]\n{toml}")).unwrap();
+        HandleRule(rules.note.into_iter().next().unwrap())
+    }
+
+    #[tokio::test]
+    async fn reaction_only() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let note = fake::note("alice", "にゃーん");
+        rule(r#"reaction = "🐈""#).handle(&note, &sango).await;
+
+        assert_eq!(
+            client.requests::<CreateReaction>(),
+            [CreateReaction::new("note", "🐈")]
+        );
+        assert!(client.notes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reaction_and_reply() {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        let note = fake::note("alice", "にゃーん");
+        rule("reaction = \"🐈\"\nresponses = [\"にゃ〜ん\"]")
+            .handle(&note, &sango)
+            .await;
+
+        assert_eq!(client.requests::<CreateReaction>().len(), 1);
+        assert_eq!(client.notes(), ["にゃ〜ん"]);
+    }
+
+    #[tokio::test]
+    async fn react_again() {
+        let client = FakeClient::default();
+        client.fail::<CreateReaction>(StatusCode::BAD_REQUEST, "ALREADY_REACTED");
+        let sango = fake::sango(&client);
+        let note = fake::note("alice", "にゃーん");
+        rule(r#"reaction = "🐈""#).handle(&note, &sango).await;
+
+        assert_eq!(
+            client.requests::<DeleteReaction>(),
+            [DeleteReaction::new("note")]
+        );
+        assert_eq!(client.requests::<CreateReaction>().len(), 2);
+    }
+
+    #[test]
+    fn invalid_exclude_pattern() {
+        let rules =
+            "[[note]]\nkeywords = [\"にゃーん\"]\nreaction = \"🐈\"\nexclude_pattern = \"(\"";
+        assert!(Rules::parse(rules).is_err());
+    }
+
+    #[test]
+    fn needs_response_or_reaction() {
+        assert!(Rules::parse("[[note]]\nkeywords = [\"にゃーん\"]").is_err());
+    }
+}
@@ -0,0 +1,144 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+// フォローをやめられたときの反応。followersの見回りで、フォロワーからいなくなったユーザーが来る
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{
+    Sango,
+    handler::{UserHandler, registry::Registry},
+    misskey::{
+        following::DeleteFollowing,
+        notes::{CreateNote, NoteVisibility},
+        users::{ShowUser, User},
+    },
+};
+
+// config.tomlの[unfollow]
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct UnfollowConfig {
+    // 秒。フォロワーの一覧を確かめる間隔
+    pub interval: u64,
+    // 秒。この間にフォローし直されたらフォローを外さない
+    pub grace_period: u64,
+    // フォローを外したときに、ダイレクトで送る言葉。省略時は何も送らない
+    pub farewell: Option<String>,
+}
+
+impl Default for UnfollowConfig {
+    fn default() -> Self {
+        Self {
+            interval: 600,
+            grace_period: 600,
+            farewell: None,
+        }
+    }
+}
+
+// 名前はconfig.tomlの[handlers.unfollow.<名前>]で使う
+pub fn register(registry: &mut Registry<User>, config: UnfollowConfig) {
+    // 勝手にフォローを外すと驚かれるので、初期状態では無効
+    registry.register("unfollowBack", 100, false, HandleUnfollowBack(config));
+}
+
+struct HandleUnfollowBack(UnfollowConfig);
+impl UserHandler for HandleUnfollowBack {
+    async fn action(&self, user: &User, sango: &Sango) -> anyhow::Result<()> {
+        tokio::time::sleep(Duration::from_secs(self.0.grace_period)).await;
+
+        // 待っている間にフォローし直されたか、もうフォローしていなければ何もしない
+        let relation = sango.client.request(ShowUser::by_user_id(&user.id)).await?;
+        if relation.is_followed || !relation.is_following {
+            return Ok(());
+        }
+        sango.client.request(DeleteFollowing::new(&user.id)).await?;
+        log::info!("Unfollowed {} back.", user.id);
+
+        if let Some(farewell) = &self.0.farewell {
+            let mention = user.mention();
+            sango
+                .client
+                .request(CreateNote {
+                    visibility: Some(NoteVisibility::Specified),
+                    visible_user_ids: vec![user.id.clone()],
+                    text: format!("{mention} {farewell}"),
+                    ..Default::default()
+                })
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        handler::registry::HandlerConfig,
+        misskey::fake::{self, FakeClient},
+        mock,
+    };
+
+    fn registry(farewell: Option<&str>) -> Registry<User> {
+        let mut registry = Registry::default();
+        let config = UnfollowConfig {
+            interval: 600,
+            grace_period: 0,
+            farewell: farewell.map(ToOwned::to_owned),
+        };
+        register(&mut registry, config);
+        let enabled = HandlerConfig {
+            enabled: Some(true),
+            priority: None,
+        };
+        registry.configure(&HashMap::from([("unfollowBack".to_owned(), enabled)]));
+        registry
+    }
+
+    fn user() -> User {
+        serde_json::from_value(mock::user("user", "alice", false)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn disabled_by_default() {
+        let client = FakeClient::default();
+        client.relation(true, false);
+        let sango = fake::sango(&client);
+        let handled = sango.handlers().unfollow.handle(&user(), &sango).await;
+
+        assert!(!handled);
+        assert!(client.requests::<DeleteFollowing>().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unfollow_back() {
+        let client = FakeClient::default();
+        client.relation(true, false);
+        let sango = fake::sango(&client);
+        registry(Some("またね")).handle(&user(), &sango).await;
+
+        assert_eq!(
+            client.requests::<DeleteFollowing>(),
+            [DeleteFollowing::new("user")]
+        );
+        assert_eq!(client.notes(), ["@alice またね"]);
+    }
+
+    #[tokio::test]
+    async fn followed_again() {
+        let client = FakeClient::default();
+        client.relation(true, true);
+        let sango = fake::sango(&client);
+        registry(Some("またね")).handle(&user(), &sango).await;
+
+        assert!(client.requests::<DeleteFollowing>().is_empty());
+        assert!(client.notes().is_empty());
+    }
+}
@@ -2,81 +2,268 @@
 //
 // SPDX-License-Identifier: UPL-1.0
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    path::PathBuf,
+    process::ExitCode,
+    sync::{Arc, atomic::AtomicBool},
+};
 
+use chrono::{DateTime, Local};
+use clap::Parser;
 use env_logger::Env;
 use rustls::crypto::ring::default_provider;
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
 
 use crate::{
+    budget::Budget,
+    catchup::{MentionCursor, RecentIds},
+    cli::{Cli, Command},
     config::Config,
-    misskey::{MisskeyClient, notes::CreateNote},
+    connection::ConnectionState,
+    cooldown::Cooldowns,
+    handler::Handlers,
+    misskey::{ApiClient, MisskeyClient, error::Unauthorized},
+    roles::Roles,
+    rules::Rules,
     savedata::SaveData,
-    websocket::MisskeyWebsocket,
+    websocket::{ChannelConfig, MisskeyWebsocket},
 };
 
+mod budget;
+mod catchup;
+mod cli;
 mod config;
+mod connection;
+mod cooldown;
+mod followers;
 mod handler;
 mod misskey;
+#[cfg(test)]
+mod mock;
+mod reminder;
+mod roles;
+mod rules;
 mod savedata;
+mod schedule;
+#[cfg(test)]
+mod tests;
+mod wakeup;
 mod websocket;
 
 struct Sango {
-    client: MisskeyClient,
+    client: Box<dyn ApiClient>,
     self_id: String,
-    admin_id: String,
+    // handlersと同じく読み込み直せるようにする
+    roles: std::sync::RwLock<Arc<Roles>>,
     savedata: RwLock<SaveData>,
+    // 管理者コマンドで読み込み直せるように、使う側はArcを複製して持っていく
+    handlers: std::sync::RwLock<Arc<Handlers>>,
+    // 購読しているチャンネル。読み込み直しても購読し直さないので、起動したときのものを使い続ける
+    channels: Vec<ChannelConfig>,
+    config_path: PathBuf,
+    rules_path: PathBuf,
+    started_at: DateTime<Local>,
+    muted: AtomicBool,                            // タイムラインに反応しない
+    opted_out: std::sync::Mutex<HashSet<String>>, // タイムラインに反応しないでほしい人
+    shutdown: Notify,
+    reminder_notify: Notify,
+    connection: RwLock<ConnectionState>,
+    recent_mentions: RecentIds,
+    mention_cursor: MentionCursor,
+    recent_notes: RecentIds, // 複数のチャンネルから同じ投稿が来ることがある
+    cooldowns: Cooldowns,
+    budget: Budget,
 }
 
 impl Sango {
-    async fn new(config: &Config) -> anyhow::Result<Self> {
-        let client = MisskeyClient::new(&config.host, &config.token);
+    async fn new(config: &Config, cli: &Cli) -> anyhow::Result<Self> {
+        let client = MisskeyClient::new(&config.base_url(), &config.token, config.rate_limit)?;
         let self_id = client.get_id_self().await?;
-        let savedata = SaveData::load().unwrap_or_else(|_| {
-            log::warn!("savedata.json is not found or cannot be read; Creating new one...");
-            SaveData::default()
-        });
+        let savedata = SaveData::open(config.storage, &cli.data_dir)?;
+        let cooldowns = Cooldowns::new(config.cooldown, savedata.cooldowns());
+        let muted = AtomicBool::new(savedata.muted());
+        let opted_out = std::sync::Mutex::new(savedata.opted_out());
         let savedata = RwLock::new(savedata);
+        let rules = Rules::load(&cli.rules_path())?;
+        let handlers = Handlers::new(rules, config);
         Ok(Self {
-            client,
+            client: Box::new(client),
             self_id,
             savedata,
-            admin_id: config.admin.clone(),
+            roles: std::sync::RwLock::new(Arc::new(Roles::from_config(config))),
+            handlers: std::sync::RwLock::new(Arc::new(handlers)),
+            channels: config.channels.clone(),
+            config_path: cli.config.clone(),
+            rules_path: cli.rules_path(),
+            started_at: Local::now(),
+            muted,
+            opted_out,
+            shutdown: Notify::new(),
+            reminder_notify: Notify::new(),
+            connection: RwLock::default(),
+            recent_mentions: RecentIds::default(),
+            mention_cursor: MentionCursor::default(),
+            recent_notes: RecentIds::default(),
+            cooldowns,
+            budget: Budget::new(config.budget.clone()),
         })
     }
 }
 
+impl Sango {
+    fn handlers(&self) -> Arc<Handlers> {
+        Arc::clone(&self.handlers.read().unwrap())
+    }
+
+    fn roles(&self) -> Arc<Roles> {
+        Arc::clone(&self.roles.read().unwrap())
+    }
+
+    // rules.tomlと、config.tomlのうちRELOADEDにあるものを読み込み直す
+    // 読み込みに失敗したら今のままにする
+    fn reload(&self) -> anyhow::Result<()> {
+        let mut config = Config::load(&self.config_path)?;
+        config.validate()?;
+        config.channels.clone_from(&self.channels);
+        let handlers = Handlers::new(Rules::load(&self.rules_path)?, &config);
+        let roles = Roles::from_config(&config);
+        *self.handlers.write().unwrap() = Arc::new(handlers);
+        *self.roles.write().unwrap() = Arc::new(roles);
+        log::info!(
+            "Reloaded {}. Restart to apply changes to {}.",
+            RELOADED.join(", "),
+            NEEDS_RESTART.join(", ")
+        );
+        Ok(())
+    }
+}
+
+// 読み込み直すもの
+const RELOADED: &[&str] = &[
+    "rules.toml",
+    "handlers",
+    "admin_commands",
+    "unfollow.grace_period",
+    "unfollow.farewell",
+    "admin",
+    "roles",
+];
+// 読み込み直さないので、変えたら再起動がいるもの
+const NEEDS_RESTART: &[&str] = &[
+    "token",
+    "host",
+    "storage",
+    "reconnect",
+    "wakeup",
+    "catchup",
+    "rate_limit",
+    "cooldown",
+    "budget",
+    "channels",
+    "unfollow.interval",
+    "schedule",
+];
+
+// トークンが拒否されたときの終了コード(sysexits.hのEX_NOPERM)
+const EXIT_UNAUTHORIZED: u8 = 77;
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
     let env = Env::new().default_filter_or("info");
     env_logger::init_from_env(env);
 
-    log::info!("Booting up...");
     default_provider().install_default().unwrap();
 
-    let conf = Config::load()?;
-    let sango = Sango::new(&conf).await?;
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        None | Some(Command::Run) => run(&cli).await,
+        Some(Command::Check) => cli::check(&cli).await,
+        Some(Command::Post { text, visibility }) => cli::post(&cli, text, *visibility).await,
+        Some(Command::DumpSavedata) => cli::dump_savedata(&cli),
+        Some(Command::ImportSavedata) => cli::import_savedata(&cli),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.is::<Unauthorized>() => {
+            log::error!("{e:#}");
+            ExitCode::from(EXIT_UNAUTHORIZED)
+        }
+        Err(e) => {
+            log::error!("{e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: &Cli) -> anyhow::Result<()> {
+    log::info!("Booting up...");
+
+    let conf = Config::load(&cli.config)?;
+    conf.validate()?;
+    let sango = Sango::new(&conf, cli).await?;
     let sango = Arc::new(sango);
 
     log::info!("Authorized as {}.", sango.self_id);
 
+    tokio::spawn(reminder::run(Arc::clone(&sango)));
+    schedule::spawn(&sango, conf.schedule.clone());
+    tokio::spawn(followers::run(Arc::clone(&sango), conf.unfollow.interval));
+
     loop {
-        let sango = Arc::clone(&sango);
-        if let Err(e) = main_loop(sango, &conf).await {
-            log::error!("{e}");
-            tokio::time::sleep(Duration::from_secs(30)).await;
+        let e = tokio::select! {
+            Err(e) = main_loop(Arc::clone(&sango), &conf) => e,
+            () = sango.shutdown.notified() => break,
+        };
+        if e.is::<Unauthorized>() {
+            // 再接続しても無駄なので終了する
+            return Err(e);
         }
+        let attempts = {
+            let mut connection = sango.connection.write().await;
+            connection.failed(&e);
+            connection.attempts
+        };
+        let delay = connection::backoff(conf.reconnect, attempts);
+        log::error!("{e:#}");
+        log::info!(
+            "Reconnecting in {:.1}s (attempt {attempts})...",
+            delay.as_secs_f64()
+        );
+        tokio::select! {
+            () = tokio::time::sleep(delay) => {}
+            () = sango.shutdown.notified() => break,
+        }
+    }
+    log::info!("Shutting down...");
+    // 反応のたびには保存していないので、最後の分を書いておく
+    if sango.cooldowns.persist() {
+        let snapshot = sango.cooldowns.snapshot(Local::now());
+        sango.savedata.write().await.set_cooldowns(&snapshot)?;
     }
+    Ok(())
 }
 
-async fn main_loop(sango: Arc<Sango>, conf: &Config) -> anyhow::Result<()> {
-    let sango = Arc::clone(&sango);
-    let mut ws = MisskeyWebsocket::new(&conf.host, &conf.token).await?;
+async fn main_loop(sango: Arc<Sango>, conf: &Config) -> anyhow::Result<Infallible> {
+    let mut ws = MisskeyWebsocket::new(&conf.base_url(), &conf.token, &conf.channels).await?;
+    let downtime = sango.connection.write().await.connected();
+
+    if let Err(e) = wakeup::announce(&sango, &conf.wakeup, downtime).await {
+        log::error!("{e}");
+    }
 
-    sango
-        .client
-        .request(CreateNote::new("うーん、うとうとしちゃってたみたい……？"))
-        .await?;
+    // 取りこぼしの処理中もwsは読み続けないといけないので別タスクで
+    if conf.catchup.enabled {
+        sango.mention_cursor.pause();
+    }
+    let catchup_sango = Arc::clone(&sango);
+    let catchup_config = conf.catchup.clone();
+    tokio::spawn(async move {
+        if let Err(e) = catchup::run(&catchup_sango, &catchup_config).await {
+            log::error!("Failed to catch up on mentions: {e}");
+        }
+    });
 
     loop {
         let next = ws.next().await?; // 接続が切れたらloopを抜ける
@@ -2,68 +2,225 @@
 //
 // SPDX-License-Identifier: UPL-1.0
 
+use std::time::Duration;
+
 use anyhow::Context;
-use reqwest::Client;
+use futures::{FutureExt, future::BoxFuture};
+use reqwest::{Client, Response, StatusCode, header::RETRY_AFTER};
 use serde::{Serialize, de::DeserializeOwned};
-use serde_json::json;
+use serde_json::{Value, json};
+
+use crate::misskey::{
+    error::{MisskeyError, Unauthorized},
+    ratelimit::{RateLimitConfig, RateLimiter},
+};
 
+pub mod chat;
+pub mod error;
+#[cfg(test)]
+pub mod fake;
 pub mod following;
 pub mod notes;
+pub mod ratelimit;
 pub mod users;
 
+// Retry-Afterが無いときに待つ時間
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
 pub trait ApiRequest {
     const ENDPOINT: &str;
     type Return;
+    // 読み取りだけなど、2回送っても困らないもの。サーバー側のエラーでもやり直す
+    const IDEMPOTENT: bool = false;
+}
+
+// APIを叩く部分。テストでは偽物に差し替える
+pub trait ApiClient: Send + Sync {
+    // ENDPOINTにparamsを送り、返ってきたJSONをそのまま返す
+    fn request_raw<'a>(
+        &'a self,
+        endpoint: &'static str,
+        params: Value,
+        idempotent: bool,
+    ) -> BoxFuture<'a, anyhow::Result<Value>>;
+}
+
+impl dyn ApiClient {
+    pub async fn request<Req>(&self, params: Req) -> anyhow::Result<Req::Return>
+    where
+        Req: Serialize + ApiRequest,
+        Req::Return: DeserializeOwned,
+    {
+        let params = serde_json::to_value(params).context("Failed to serialize the request")?;
+        let ret = self
+            .request_raw(Req::ENDPOINT, params, Req::IDEMPOTENT)
+            .await?;
+        serde_json::from_value(ret).context("Failed to parse the response")
+    }
 }
 
 pub struct MisskeyClient {
     client: Client,
-    host: String,
+    base_url: String, // "https://example.com"
     token: String,
+    limiter: RateLimiter,
+    max_retries: u32,
 }
 
 impl MisskeyClient {
-    pub fn new(host: &str, token: &str) -> Self {
+    pub fn new(base_url: &str, token: &str, rate_limit: RateLimitConfig) -> anyhow::Result<Self> {
         let client = Client::new();
-        let host = host.to_owned();
+        let base_url = base_url.to_owned();
         let token = token.to_owned();
-        Self {
+        Ok(Self {
             client,
-            host,
+            base_url,
             token,
-        }
+            limiter: RateLimiter::new(rate_limit)?,
+            max_retries: rate_limit.max_retries,
+        })
     }
 
     pub async fn get_id_self(&self) -> anyhow::Result<String> {
-        let host = &self.host;
+        let base_url = &self.base_url;
         let resp = self
             .client
-            .post(format!("https://{host}/api/i"))
+            .post(format!("{base_url}/api/i"))
             .bearer_auth(&self.token)
             .json(&json!({}))
             .send()
             .await
             .context("Failed to authorize")?;
+        if matches!(
+            resp.status(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            return Err(Unauthorized.into());
+        }
         let i: users::User = resp.json().await.context("Failed to authorize")?;
         Ok(i.id)
     }
 
-    pub async fn request<Req>(&self, params: Req) -> anyhow::Result<Req::Return>
-    where
-        Req: Serialize + ApiRequest,
-        Req::Return: DeserializeOwned,
-    {
-        let host = &self.host;
-        let resp = self
-            .client
-            .post(format!("https://{host}{}", Req::ENDPOINT))
-            .bearer_auth(&self.token)
-            .json(&params)
-            .send()
-            .await
-            .context("Failed to send request")?;
-        let resp = resp.error_for_status()?;
-        let ret = resp.json().await.context("Failed to parse the response")?;
-        Ok(ret)
+    async fn send(
+        &self,
+        endpoint: &str,
+        params: &Value,
+        idempotent: bool,
+    ) -> anyhow::Result<Value> {
+        let base_url = &self.base_url;
+        let mut retries = 0;
+        loop {
+            self.limiter.acquire().await;
+            let resp = self
+                .client
+                .post(format!("{base_url}{endpoint}"))
+                .bearer_auth(&self.token)
+                .json(params)
+                .send()
+                .await
+                .context("Failed to send request")?;
+
+            let status = resp.status();
+            let retry_after = retry_after(&resp);
+            let body = resp
+                .bytes()
+                .await
+                .context("Failed to receive the response")?;
+            if status.is_success() {
+                // 204 No Contentなど、中身が無いこともある
+                let body = if body.is_empty() { b"null" } else { &body[..] };
+                let ret = serde_json::from_slice(body).context("Failed to parse the response")?;
+                return Ok(ret);
+            }
+
+            let error = MisskeyError::from_response(status, &body);
+            let rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+                || matches!(error, MisskeyError::RateLimitExceeded);
+            // 書き込みは済んでいるかもしれないので、2回送っても困らないものだけやり直す
+            let server_error = idempotent && error.is_server_error();
+            if (rate_limited || server_error) && retries < self.max_retries {
+                retries += 1;
+                let wait = retry_after.unwrap_or(DEFAULT_RETRY_AFTER * retries);
+                if rate_limited {
+                    log::warn!(
+                        "Rate limited on {endpoint}; Retrying in {}s...",
+                        wait.as_secs()
+                    );
+                    // ほかのリクエストも巻き込んで待つ
+                    self.limiter.pause(wait);
+                } else {
+                    // サーバー側のエラーは、このリクエストだけ待ってやり直す
+                    log::warn!(
+                        "{endpoint} failed with a server error ({error}); Retrying in {}s...",
+                        wait.as_secs()
+                    );
+                    tokio::time::sleep(wait).await;
+                }
+                continue;
+            }
+            return Err(api_error(endpoint, status, error));
+        }
+    }
+}
+
+impl ApiClient for MisskeyClient {
+    fn request_raw<'a>(
+        &'a self,
+        endpoint: &'static str,
+        params: Value,
+        idempotent: bool,
+    ) -> BoxFuture<'a, anyhow::Result<Value>> {
+        async move { self.send(endpoint, &params, idempotent).await }.boxed()
+    }
+}
+
+// 呼び出し側からはdowncast_refでMisskeyErrorやUnauthorizedを取り出せる
+pub fn api_error(endpoint: &str, status: StatusCode, error: MisskeyError) -> anyhow::Error {
+    if status == StatusCode::UNAUTHORIZED {
+        return anyhow::Error::new(Unauthorized).context(error);
+    }
+    anyhow::Error::new(error).context(format!("{endpoint} failed"))
+}
+
+// Retry-Afterは秒数の形式だけ対応する
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let secs = resp.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    secs.trim().parse().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        misskey::{notes::CreateNote, ratelimit::RateLimitConfig},
+        mock::{self, MockMisskey},
+    };
+
+    #[tokio::test]
+    async fn no_retry_on_write() {
+        let server = MockMisskey::start().await;
+        let error = json!({
+            "error": {
+                "code": "INTERNAL_ERROR",
+                "message": "",
+                "id": "",
+                "kind": "server",
+            },
+        });
+        server.set_response(
+            "/api/notes/create",
+            StatusCode::INTERNAL_SERVER_ERROR,
+            error,
+        );
+        let config = RateLimitConfig {
+            requests_per_second: 0.0,
+            max_retries: 3,
+        };
+        let client: &dyn ApiClient =
+            &MisskeyClient::new(server.base_url(), mock::TOKEN, config).unwrap();
+
+        // 投稿は済んでいるかもしれないので、やり直さない
+        assert!(client.request(CreateNote::new("テスト")).await.is_err());
+        assert_eq!(server.requests("/api/notes/create").len(), 1);
     }
 }
@@ -0,0 +1,92 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize, de::IgnoredAny};
+
+use crate::misskey::{
+    ApiRequest,
+    notes::{Note, NoteVisibility},
+    users::User,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMessage {
+    pub id: String,
+    pub created_at: DateTime<Local>,
+    pub text: Option<String>, // ファイルだけのときはnull
+    pub from_user_id: String,
+    pub from_user: User,
+    // pub to_user_id: Option<String>, // Unused
+    // ルームのメッセージなら入っている
+    pub to_room_id: Option<String>,
+    // pub file_id: Option<String>, // Unused
+    // pub is_read: bool, // Unused
+    // pub reactions: todo!(), // めんどくさいしたぶん使わない
+}
+
+impl ChatMessage {
+    // メンションと同じHandlerで処理できるように、ダイレクトのノートとして扱う
+    // 本文のないものはNone
+    pub fn into_note(self) -> Option<Note> {
+        Some(Note {
+            id: self.id,
+            created_at: self.created_at,
+            text: self.text?,
+            user_id: self.from_user_id,
+            user: self.from_user,
+            reply_id: None,
+            renote_id: None,
+            reply_to: None,
+            visibility: NoteVisibility::Specified,
+            mentions: Vec::new(),
+            chat: true,
+        })
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq, Deserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateChatMessage {
+    to_user_id: String,
+    text: String,
+}
+
+impl CreateChatMessage {
+    pub fn new(to_user_id: &str, text: &str) -> Self {
+        Self {
+            to_user_id: to_user_id.to_owned(),
+            text: text.to_owned(),
+        }
+    }
+}
+
+impl ApiRequest for CreateChatMessage {
+    const ENDPOINT: &str = "/api/chat/messages/create-to-user";
+    type Return = IgnoredAny; // 中身は使わない
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq, Deserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateChatReaction {
+    message_id: String,
+    reaction: String,
+}
+
+impl CreateChatReaction {
+    pub fn new(message_id: &str, reaction: &str) -> Self {
+        Self {
+            message_id: message_id.to_owned(),
+            reaction: reaction.to_owned(),
+        }
+    }
+}
+
+impl ApiRequest for CreateChatReaction {
+    const ENDPOINT: &str = "/api/chat/messages/react";
+    type Return = IgnoredAny; // 204 No Content
+}
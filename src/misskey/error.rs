@@ -0,0 +1,162 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::fmt::Display;
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+// トークンが間違っているなど、何度やり直しても無駄なエラー
+#[derive(Debug)]
+pub struct Unauthorized;
+
+impl Display for Unauthorized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The token was rejected by the server")
+    }
+}
+
+impl std::error::Error for Unauthorized {}
+
+// Misskeyが返すエラーの中身
+#[derive(Debug, Deserialize)]
+pub struct ApiError {
+    pub code: String,
+    pub message: String,
+    pub id: String,
+    // 古いサーバーなどで無いこともある
+    #[serde(default)]
+    pub kind: Option<ErrorKind>,
+}
+
+// clientならリクエストのほうが悪いので、やり直しても無駄
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    Client,
+    Server,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ApiError,
+}
+
+#[derive(Debug)]
+pub enum MisskeyError {
+    RateLimitExceeded,
+    NoSuchUser,
+    NoSuchNote,
+    AlreadyFollowing,
+    NotFollowing,
+    AlreadyReacted,
+    NotReacted,
+    Blocking,
+    Blocked,
+    PermissionDenied,
+    Api(StatusCode, ApiError), // 上以外のエラー
+    Http(StatusCode, String),  // Misskeyの形式ですらないエラー
+}
+
+impl MisskeyError {
+    pub fn from_response(status: StatusCode, body: &[u8]) -> Self {
+        let Ok(ErrorResponse { error }) = serde_json::from_slice(body) else {
+            return Self::Http(status, String::from_utf8_lossy(body).into_owned());
+        };
+        match error.code.as_str() {
+            "RATE_LIMIT_EXCEEDED" => Self::RateLimitExceeded,
+            "NO_SUCH_USER" => Self::NoSuchUser,
+            "NO_SUCH_NOTE" => Self::NoSuchNote,
+            "ALREADY_FOLLOWING" => Self::AlreadyFollowing,
+            "NOT_FOLLOWING" => Self::NotFollowing,
+            "ALREADY_REACTED" => Self::AlreadyReacted,
+            "NOT_REACTED" => Self::NotReacted,
+            "BLOCKING" => Self::Blocking,
+            "BLOCKED" => Self::Blocked,
+            "PERMISSION_DENIED" => Self::PermissionDenied,
+            _ if status == StatusCode::TOO_MANY_REQUESTS => Self::RateLimitExceeded,
+            _ => Self::Api(status, error),
+        }
+    }
+}
+
+impl MisskeyError {
+    // サーバー側のエラー。やり直せば通るかもしれないが、書き込みは済んでいることもある
+    pub fn is_server_error(&self) -> bool {
+        match self {
+            Self::Api(_, error) => error.kind == Some(ErrorKind::Server),
+            Self::Http(status, _) => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+impl Display for MisskeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RateLimitExceeded => write!(f, "Rate limit exceeded"),
+            Self::NoSuchUser => write!(f, "No such user"),
+            Self::NoSuchNote => write!(f, "No such note"),
+            Self::AlreadyFollowing => write!(f, "Already following"),
+            Self::NotFollowing => write!(f, "Not following"),
+            Self::AlreadyReacted => write!(f, "Already reacted"),
+            Self::NotReacted => write!(f, "Not reacted"),
+            Self::Blocking => write!(f, "Blocking the user"),
+            Self::Blocked => write!(f, "Blocked by the user"),
+            Self::PermissionDenied => write!(f, "Permission denied"),
+            Self::Api(status, error) => write!(
+                f,
+                "{status} {}: {} (id: {})",
+                error.code, error.message, error.id
+            ),
+            Self::Http(status, body) => write!(f, "{status}: {body}"),
+        }
+    }
+}
+
+impl std::error::Error for MisskeyError {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn error(status: StatusCode, code: &str, kind: &str) -> MisskeyError {
+        let body = json!({
+            "error": {
+                "code": code,
+                "message": "",
+                "id": "",
+                "kind": kind,
+            },
+        });
+        MisskeyError::from_response(status, body.to_string().as_bytes())
+    }
+
+    #[test]
+    fn server_error() {
+        assert!(
+            error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "INTERNAL_ERROR",
+                "server"
+            )
+            .is_server_error()
+        );
+        assert!(
+            !error(
+                StatusCode::TOO_MANY_REQUESTS,
+                "RATE_LIMIT_EXCEEDED",
+                "client"
+            )
+            .is_server_error()
+        );
+        assert!(!error(StatusCode::BAD_REQUEST, "INVALID_PARAM", "client").is_server_error());
+        let http = MisskeyError::from_response(StatusCode::BAD_GATEWAY, b"Bad Gateway");
+        assert!(http.is_server_error());
+        let http = MisskeyError::from_response(StatusCode::NOT_FOUND, b"Not Found");
+        assert!(!http.is_server_error());
+    }
+}
@@ -0,0 +1,169 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+// テスト用の偽クライアント。送られたリクエストを覚えておき、決めておいた返事を返す
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex, atomic::AtomicBool},
+};
+
+use chrono::Local;
+use futures::{FutureExt, future::BoxFuture};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use tokio::sync::{Notify, RwLock};
+
+use crate::{
+    Sango,
+    budget::{Budget, BudgetConfig},
+    catchup::{MentionCursor, RecentIds},
+    config::Config,
+    cooldown::{CooldownConfig, Cooldowns},
+    handler::Handlers,
+    misskey::{
+        ApiClient, ApiRequest, api_error,
+        error::MisskeyError,
+        notes::{CreateNote, Note},
+        users::ShowUser,
+    },
+    mock,
+    roles::Roles,
+    rules::{self, Rules},
+    savedata::SaveData,
+};
+
+pub const ADMIN_ID: &str = "admin";
+pub const MODERATOR_ID: &str = "moderator";
+
+type Response = Result<Value, (StatusCode, Value)>;
+
+// Sangoに渡したあとも中身を覗けるように、clone同士で共有する
+#[derive(Clone, Default)]
+pub struct FakeClient(Arc<Mutex<Inner>>);
+
+#[derive(Default)]
+struct Inner {
+    requests: Vec<(&'static str, Value)>,
+    responses: HashMap<&'static str, Response>,
+}
+
+impl FakeClient {
+    // Reqへの返事を決める。決めていなければ{}が返る
+    pub fn respond<Req: ApiRequest>(&self, ret: Value) {
+        let mut inner = self.0.lock().unwrap();
+        inner.responses.insert(Req::ENDPOINT, Ok(ret));
+    }
+
+    // Reqを失敗させる。codeはMisskeyのエラーコード("NO_SUCH_USER"など)
+    pub fn fail<Req: ApiRequest>(&self, status: StatusCode, code: &str) {
+        let body = json!({
+            "error": {
+                "code": code,
+                "message": "",
+                "id": "",
+            },
+        });
+        let mut inner = self.0.lock().unwrap();
+        inner.responses.insert(Req::ENDPOINT, Err((status, body)));
+    }
+
+    // ShowUserで返すフォロー関係
+    pub fn relation(&self, is_following: bool, is_followed: bool) {
+        self.respond::<ShowUser>(json!({
+            "id": "user",
+            "isFollowing": is_following,
+            "isFollowed": is_followed,
+        }));
+    }
+
+    // 送られたReqを古い順に
+    pub fn requests<Req: ApiRequest + DeserializeOwned>(&self) -> Vec<Req> {
+        let inner = self.0.lock().unwrap();
+        inner
+            .requests
+            .iter()
+            .filter(|(endpoint, _)| *endpoint == Req::ENDPOINT)
+            .map(|(_, params)| serde_json::from_value(params.clone()).unwrap())
+            .collect()
+    }
+
+    // 投稿した本文を古い順に
+    pub fn notes(&self) -> Vec<String> {
+        self.requests::<CreateNote>()
+            .into_iter()
+            .map(|note| note.text)
+            .collect()
+    }
+}
+
+impl ApiClient for FakeClient {
+    fn request_raw<'a>(
+        &'a self,
+        endpoint: &'static str,
+        params: Value,
+        _idempotent: bool,
+    ) -> BoxFuture<'a, anyhow::Result<Value>> {
+        let mut inner = self.0.lock().unwrap();
+        inner.requests.push((endpoint, params));
+        let response = inner
+            .responses
+            .get(endpoint)
+            .cloned()
+            .unwrap_or_else(|| Ok(json!({})));
+        drop(inner);
+        async move {
+            response.map_err(|(status, body)| {
+                let error = MisskeyError::from_response(status, body.to_string().as_bytes());
+                api_error(endpoint, status, error)
+            })
+        }
+        .boxed()
+    }
+}
+
+// 必須の項目だけ埋めた設定
+pub fn config() -> Config {
+    toml::from_str(&format!(
+        "token = \"{}\"\nhost = \"localhost\"\nadmin = \"{ADMIN_ID}\"\n[roles]\nmoderator = [\"{MODERATOR_ID}\"]",
+        mock::TOKEN
+    ))
+    .unwrap()
+}
+
+// 偽クライアントと空の保存先を持ったSango。ルールはrules_example.toml
+pub fn sango(client: &FakeClient) -> Sango {
+    Sango {
+        client: Box::new(client.clone()),
+        self_id: mock::SELF_ID.to_owned(),
+        roles: std::sync::RwLock::new(Arc::new(Roles::from_config(&config()))),
+        savedata: RwLock::new(SaveData::in_memory()),
+        handlers: std::sync::RwLock::new(Arc::new(Handlers::new(
+            Rules::parse(rules::DEFAULT_RULES).unwrap(),
+            &config(),
+        ))),
+        channels: config().channels,
+        config_path: PathBuf::from("config.toml"),
+        rules_path: PathBuf::from("rules.toml"),
+        started_at: Local::now(),
+        muted: AtomicBool::new(false),
+        opted_out: Mutex::default(),
+        shutdown: Notify::new(),
+        reminder_notify: Notify::new(),
+        connection: RwLock::default(),
+        recent_mentions: RecentIds::default(),
+        mention_cursor: MentionCursor::default(),
+        recent_notes: RecentIds::default(),
+        cooldowns: Cooldowns::new(CooldownConfig::default(), HashMap::new()),
+        budget: Budget::new(BudgetConfig::default()),
+    }
+}
+
+// user_idのユーザーからのノート。"@sango"を含めばメンションになる
+pub fn note(user_id: &str, text: &str) -> Note {
+    let user = mock::user(user_id, user_id, false);
+    serde_json::from_value(mock::note("note", &user, text)).unwrap()
+}
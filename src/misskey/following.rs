@@ -2,11 +2,12 @@
 //
 // SPDX-License-Identifier: UPL-1.0
 
-use serde::Serialize;
+use serde::{Serialize, de::IgnoredAny};
 
 use crate::misskey::ApiRequest;
 
 #[derive(Clone, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq, serde::Deserialize))]
 #[serde(rename_all = "camelCase")]
 pub struct CreateFollowing {
     user_id: String,
@@ -22,10 +23,11 @@ impl CreateFollowing {
 
 impl ApiRequest for CreateFollowing {
     const ENDPOINT: &str = "/api/following/create";
-    type Return = ();
+    type Return = IgnoredAny; // 中身は使わない
 }
 
 #[derive(Clone, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq, serde::Deserialize))]
 #[serde(rename_all = "camelCase")]
 pub struct DeleteFollowing {
     user_id: String,
@@ -41,5 +43,25 @@ impl DeleteFollowing {
 
 impl ApiRequest for DeleteFollowing {
     const ENDPOINT: &str = "/api/following/delete";
-    type Return = ();
+    type Return = IgnoredAny; // 中身は使わない
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq, serde::Deserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptFollowRequest {
+    user_id: String,
+}
+
+impl AcceptFollowRequest {
+    pub fn new(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_owned(),
+        }
+    }
+}
+
+impl ApiRequest for AcceptFollowRequest {
+    const ENDPOINT: &str = "/api/following/requests/accept";
+    type Return = IgnoredAny; // 中身は使わない
 }
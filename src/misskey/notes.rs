@@ -2,11 +2,12 @@
 //
 // SPDX-License-Identifier: UPL-1.0
 
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Deserializer, Serialize, de::IgnoredAny};
 
 use crate::misskey::{ApiRequest, users::User};
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, Serialize, Deserialize, clap::ValueEnum)]
 #[serde(rename_all = "camelCase")]
 pub enum NoteVisibility {
     Public,
@@ -28,6 +29,7 @@ pub enum ReactionAcceptance {
 */
 
 #[derive(Clone, Default, Serialize)]
+#[cfg_attr(test, derive(Deserialize), serde(default))]
 #[serde(rename_all = "camelCase")]
 pub struct CreatePoll {
     pub choices: Vec<String>,
@@ -51,6 +53,7 @@ pub struct Poll {
 */
 
 #[derive(Clone, Default, Serialize)]
+#[cfg_attr(test, derive(Deserialize), serde(default))] // テストで送った中身を確かめる用
 #[serde(rename_all = "camelCase")]
 pub struct CreateNote {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -97,27 +100,34 @@ impl CreateNote {
 
 impl ApiRequest for CreateNote {
     const ENDPOINT: &str = "/api/notes/create";
-    type Return = ();
+    type Return = IgnoredAny; // 中身は使わない
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Note {
     pub id: String,
-    // pub created_at: String, // Unused
+    pub created_at: DateTime<Local>,
     // pub deleted_at: Option<String>, // Unused
+    // 本文のないノート(リノートやファイルだけのもの)はnullなので空文字列にする
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub text: String,
     // pub cw: Option<String>, // Unused
     pub user_id: String,
     pub user: User,
     pub reply_id: Option<String>,
-    // pub renote_id: Option<String>, // Unused
-    // pub reply: todo!(), // めんどくさいしたぶん使わない
+    pub renote_id: Option<String>,
+    // 返信先のノート。返信でなければnull
+    #[serde(default, rename = "reply")]
+    pub reply_to: Option<Box<Self>>,
     // pub renote: todo!(), // めんどくさいしたぶん使わない
     // pub is_hidden: bool, // 謎
     pub visibility: NoteVisibility,
     #[serde(default)]
     pub mentions: Vec<String>,
+    // チャットのメッセージをノートとして扱っているときはtrue
+    #[serde(skip)]
+    pub chat: bool,
     // pub visible_user_ids: Vec<String>, // 謎
     // pub file_ids: Vec<String>, // Unused
     // pub files: todo!(), // めんどくさいしたぶん使わない
@@ -128,13 +138,107 @@ pub struct Note {
     // pub channel: todo!(), // めんどくさいしたぶん使わない
 }
 
+fn null_as_empty<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
 impl Note {
+    // 本文付きのリノート
+    pub const fn is_quote(&self) -> bool {
+        self.renote_id.is_some() && !self.text.is_empty()
+    }
+
+    pub fn is_reply_to(&self, user_id: &str) -> bool {
+        self.reply_to
+            .as_ref()
+            .is_some_and(|reply_to| reply_to.user_id == user_id)
+    }
+
     pub fn reply(&self, text: &str) -> CreateNote {
         CreateNote {
             visibility: Some(self.visibility), // 公開範囲を受け取ったノートに合わせる
             reply_id: Some(self.id.clone()),   // 返信
+            // ダイレクトなら相手に見えるようにする
+            visible_user_ids: if matches!(self.visibility, NoteVisibility::Specified) {
+                vec![self.user_id.clone()]
+            } else {
+                Vec::new()
+            },
             text: text.to_owned(),
             ..Default::default()
         }
     }
+
+    pub fn react(&self, reaction: &str) -> CreateReaction {
+        CreateReaction::new(&self.id, reaction)
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq, Deserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReaction {
+    note_id: String,
+    reaction: String, // "👍"や":blobcat:"
+}
+
+impl CreateReaction {
+    pub fn new(note_id: &str, reaction: &str) -> Self {
+        Self {
+            note_id: note_id.to_owned(),
+            reaction: reaction.to_owned(),
+        }
+    }
+}
+
+impl ApiRequest for CreateReaction {
+    const ENDPOINT: &str = "/api/notes/reactions/create";
+    type Return = IgnoredAny; // 204 No Content
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq, Deserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteReaction {
+    note_id: String,
+}
+
+impl DeleteReaction {
+    pub fn new(note_id: &str) -> Self {
+        Self {
+            note_id: note_id.to_owned(),
+        }
+    }
+}
+
+impl ApiRequest for DeleteReaction {
+    const ENDPOINT: &str = "/api/notes/reactions/delete";
+    type Return = IgnoredAny; // 204 No Content
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mentions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until_id: Option<String>,
+}
+
+impl Mentions {
+    pub fn since(since_id: &str, limit: u32) -> Self {
+        Self {
+            limit: Some(limit),
+            since_id: Some(since_id.to_owned()),
+            ..Default::default()
+        }
+    }
+}
+
+impl ApiRequest for Mentions {
+    const ENDPOINT: &str = "/api/notes/mentions";
+    type Return = Vec<Note>;
+    const IDEMPOTENT: bool = true;
 }
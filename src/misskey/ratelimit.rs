@@ -0,0 +1,174 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::{collections::VecDeque, future::Future, time::Duration};
+
+use serde::Deserialize;
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    // 1秒あたりに送るリクエストの数。0なら制限しない
+    pub requests_per_second: f64,
+    // レート制限に引っかかったときや、読み取りのリクエストがサーバー側のエラーになったときにやり直す回数
+    pub max_retries: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 2.0,
+            max_retries: 3,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    // リクエストの間隔。制限しないならNone
+    pub fn interval(&self) -> anyhow::Result<Option<Duration>> {
+        let rps = self.requests_per_second;
+        anyhow::ensure!(
+            rps.is_finite() && rps >= 0.0,
+            "rate_limit.requests_per_second must be 0 or a positive number"
+        );
+        if rps == 0.0 {
+            return Ok(None);
+        }
+        let interval = Duration::try_from_secs_f64(1.0 / rps)
+            .map_err(|_| anyhow::anyhow!("rate_limit.requests_per_second is too small: {rps}"))?;
+        Ok(Some(interval))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    High, // メンションへの返信など
+    Normal,
+}
+
+tokio::task_local! {
+    static PRIORITY: Priority;
+}
+
+// fの中から送るリクエストの優先度を変える
+pub async fn with_priority<F: Future>(priority: Priority, f: F) -> F::Output {
+    PRIORITY.scope(priority, f).await
+}
+
+fn current_priority() -> Priority {
+    PRIORITY
+        .try_with(|priority| *priority)
+        .unwrap_or(Priority::Normal)
+}
+
+enum Message {
+    Acquire(Priority, oneshot::Sender<()>),
+    Pause(Duration),
+}
+
+// 送信の順番を決める。優先度の高いものから順に、一定間隔で送っていい合図を出す
+pub struct RateLimiter(Option<mpsc::UnboundedSender<Message>>);
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> anyhow::Result<Self> {
+        let Some(interval) = config.interval()? else {
+            return Ok(Self(None));
+        };
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(dispatch(rx, interval));
+        Ok(Self(Some(tx)))
+    }
+
+    pub async fn acquire(&self) {
+        let Some(tx) = &self.0 else {
+            return;
+        };
+        let (permit_tx, permit_rx) = oneshot::channel();
+        if tx
+            .send(Message::Acquire(current_priority(), permit_tx))
+            .is_ok()
+        {
+            // 送信側が消えていたら待たずに送る
+            let _ = permit_rx.await;
+        }
+    }
+
+    // 全部のリクエストをしばらく止める
+    pub fn pause(&self, duration: Duration) {
+        if let Some(tx) = &self.0 {
+            let _ = tx.send(Message::Pause(duration));
+        }
+    }
+}
+
+async fn dispatch(mut rx: mpsc::UnboundedReceiver<Message>, interval: Duration) {
+    let mut high = VecDeque::new();
+    let mut normal = VecDeque::new();
+    let mut next = Instant::now();
+
+    loop {
+        if high.is_empty() && normal.is_empty() {
+            let Some(message) = rx.recv().await else {
+                return;
+            };
+            receive(message, &mut high, &mut normal, &mut next);
+        }
+        while let Ok(message) = rx.try_recv() {
+            receive(message, &mut high, &mut normal, &mut next);
+        }
+
+        if Instant::now() < next {
+            tokio::time::sleep_until(next).await;
+            continue; // 待っている間に来たものも含めて選び直す
+        }
+
+        let Some(permit) = high.pop_front().or_else(|| normal.pop_front()) else {
+            continue;
+        };
+        // 待つのをやめていたら枠を使わない
+        if permit.send(()).is_ok() {
+            next = Instant::now() + interval;
+        }
+    }
+}
+
+fn receive(
+    message: Message,
+    high: &mut VecDeque<oneshot::Sender<()>>,
+    normal: &mut VecDeque<oneshot::Sender<()>>,
+    next: &mut Instant,
+) {
+    match message {
+        Message::Acquire(Priority::High, permit) => high.push_back(permit),
+        Message::Acquire(Priority::Normal, permit) => normal.push_back(permit),
+        Message::Pause(duration) => *next = (*next).max(Instant::now() + duration),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(requests_per_second: f64) -> anyhow::Result<Option<Duration>> {
+        RateLimitConfig {
+            requests_per_second,
+            ..Default::default()
+        }
+        .interval()
+    }
+
+    #[test]
+    fn invalid_rate() {
+        assert_eq!(interval(0.0).unwrap(), None);
+        assert_eq!(interval(2.0).unwrap(), Some(Duration::from_millis(500)));
+        assert!(interval(f64::NAN).is_err());
+        assert!(interval(f64::INFINITY).is_err());
+        assert!(interval(-1.0).is_err());
+        assert!(interval(f64::MIN_POSITIVE / 2.0).is_err());
+    }
+}
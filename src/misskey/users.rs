@@ -7,8 +7,10 @@ use serde::{Deserialize, Serialize};
 use crate::misskey::ApiRequest;
 
 #[derive(Default, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq, Deserialize), serde(default))]
 #[serde(rename_all = "camelCase")]
 pub struct ShowUser {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub user_id: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub user_ids: Vec<String>,
@@ -26,21 +28,19 @@ impl ShowUser {
         }
     }
 
-    // Unused
-    /*
     pub fn by_username(username: &str, host: Option<&str>) -> Self {
         Self {
             username: Some(username.to_owned()),
-            host: host.map(|ch| ch.to_owned()),
+            host: host.map(ToOwned::to_owned),
             ..Default::default()
         }
     }
-    */
 }
 
 impl ApiRequest for ShowUser {
     const ENDPOINT: &str = "/api/users/show";
     type Return = UserDetailed;
+    const IDEMPOTENT: bool = true;
 }
 
 // Unused
@@ -84,7 +84,41 @@ impl User {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UserDetailed {
+    pub id: String,
     pub is_following: bool,
     pub is_followed: bool,
     // いまのところフォロー関係以外に興味なし
 }
+
+// あるユーザーのフォロワー。新しくフォローされた順
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Followers {
+    user_id: String,
+    limit: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    until_id: Option<String>,
+}
+
+impl Followers {
+    pub fn new(user_id: &str, limit: u32, until_id: Option<&str>) -> Self {
+        Self {
+            user_id: user_id.to_owned(),
+            limit,
+            until_id: until_id.map(ToOwned::to_owned),
+        }
+    }
+}
+
+impl ApiRequest for Followers {
+    const ENDPOINT: &str = "/api/users/followers";
+    type Return = Vec<Following>;
+    const IDEMPOTENT: bool = true;
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Following {
+    pub id: String,
+    pub follower: User,
+}
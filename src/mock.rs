@@ -0,0 +1,276 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+// テスト用の偽Misskeyサーバー。/api/*と/streamingだけ真似する
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Context;
+use futures::{SinkExt, StreamExt};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_tungstenite::tungstenite::{Message, Utf8Bytes};
+
+use crate::websocket::MAIN_CHANNEL_ID;
+
+pub const SELF_ID: &str = "sango";
+pub const TOKEN: &str = "token";
+
+// これだけ待っても来なければテスト失敗
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct State {
+    // 受け取ったAPIリクエスト(エンドポイント、中身)
+    requests: Mutex<Vec<(String, Value)>>,
+    responses: Mutex<HashMap<String, (StatusCode, Value)>>,
+    // 繋がっているストリーミングへの送り口
+    streams: Mutex<Vec<mpsc::UnboundedSender<String>>>,
+}
+
+pub struct MockMisskey {
+    base_url: String,
+    state: Arc<State>,
+}
+
+impl MockMisskey {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(State::default());
+        let server_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = Arc::clone(&server_state);
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &state).await {
+                        log::error!("Mock server: {e:#}");
+                    }
+                });
+            }
+        });
+        Self { base_url, state }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // endpointへの返事を差し替える
+    pub fn set_response(&self, endpoint: &str, status: StatusCode, body: Value) {
+        self.state
+            .responses
+            .lock()
+            .unwrap()
+            .insert(endpoint.to_owned(), (status, body));
+    }
+
+    // BOTが繋いでくるのを待ってから、mainチャンネルのイベントとして流す
+    pub async fn push(&self, event_type: &str, body: Value) {
+        self.push_to(MAIN_CHANNEL_ID, event_type, body).await;
+    }
+
+    // 1つ目のタイムライン(ホームタイムライン)の投稿として流す
+    pub async fn push_note(&self, body: Value) {
+        self.push_to("0", "note", body).await;
+    }
+
+    async fn push_to(&self, id: &str, event_type: &str, body: Value) {
+        wait_until(|| !self.state.streams.lock().unwrap().is_empty()).await;
+        let event = json!({
+            "type": "channel",
+            "body": {
+                "id": id,
+                "type": event_type,
+                "body": body,
+            },
+        });
+        for stream in self.state.streams.lock().unwrap().iter() {
+            let _ = stream.send(event.to_string());
+        }
+    }
+
+    pub fn requests(&self, endpoint: &str) -> Vec<Value> {
+        self.state
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path == endpoint)
+            .map(|(_, body)| body.clone())
+            .collect()
+    }
+
+    // endpointへのリクエストがcount個来るまで待つ
+    pub async fn wait_for(&self, endpoint: &str, count: usize) -> Vec<Value> {
+        wait_until(|| self.requests(endpoint).len() >= count).await;
+        self.requests(endpoint)
+    }
+}
+
+async fn wait_until(mut cond: impl FnMut() -> bool) {
+    tokio::time::timeout(TIMEOUT, async {
+        while !cond() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Timed out waiting for the bot");
+}
+
+async fn serve(mut stream: TcpStream, state: &State) -> anyhow::Result<()> {
+    let mut head = [0; 16];
+    let n = stream.peek(&mut head).await?;
+    if head[..n].starts_with(b"GET /streaming") {
+        return streaming(stream, state).await;
+    }
+
+    let (path, body) = read_request(&mut stream).await?;
+    state.requests.lock().unwrap().push((path.clone(), body));
+    let (status, body) = state
+        .responses
+        .lock()
+        .unwrap()
+        .get(&path)
+        .cloned()
+        .unwrap_or_else(|| (StatusCode::OK, default_response(&path)));
+
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default(),
+        body.len(),
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+// HTTP/1.1のリクエストからパスとJSONの中身だけ取り出す
+async fn read_request(stream: &mut TcpStream) -> anyhow::Result<(String, Value)> {
+    let mut buf = Vec::new();
+    let header_end = loop {
+        if let Some(pos) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let mut chunk = [0; 1024];
+        let n = stream.read(&mut chunk).await?;
+        anyhow::ensure!(n > 0, "Connection closed in the middle of the header");
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let header = String::from_utf8_lossy(&buf[..header_end]).into_owned();
+    let path = header
+        .split_whitespace()
+        .nth(1)
+        .context("No request path")?
+        .to_owned();
+    let length = header
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0);
+
+    let mut body = buf.split_off(header_end);
+    while body.len() < length {
+        let mut chunk = [0; 1024];
+        let n = stream.read(&mut chunk).await?;
+        anyhow::ensure!(n > 0, "Connection closed in the middle of the body");
+        body.extend_from_slice(&chunk[..n]);
+    }
+    let body = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&body)?
+    };
+    Ok((path, body))
+}
+
+async fn streaming(stream: TcpStream, state: &State) -> anyhow::Result<()> {
+    let mut ws = tokio_tungstenite::accept_async(stream).await?;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    state.streams.lock().unwrap().push(tx);
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else {
+                    return Ok(());
+                };
+                ws.send(Message::Text(Utf8Bytes::from(event))).await?;
+            }
+            message = ws.next() => {
+                // チャンネルへの接続要求などは読み捨てる
+                if message.is_none_or(|message| message.is_err()) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn default_response(path: &str) -> Value {
+    match path {
+        "/api/i" => user(SELF_ID, "sango", true),
+        "/api/users/show" => json!({
+            "id": "user",
+            "isFollowing": false,
+            "isFollowed": true,
+        }),
+        "/api/notes/mentions" => json!([]),
+        _ => json!({}),
+    }
+}
+
+pub fn user(id: &str, username: &str, is_bot: bool) -> Value {
+    json!({
+        "id": id,
+        "name": null,
+        "username": username,
+        "host": null,
+        "isBot": is_bot,
+    })
+}
+
+// BOTに直接送られたチャットのメッセージ
+pub fn chat_message(id: &str, user: &Value, text: &str) -> Value {
+    json!({
+        "id": id,
+        "createdAt": chrono::Utc::now().to_rfc3339(),
+        "text": text,
+        "fromUserId": user["id"],
+        "fromUser": user,
+        "toUserId": SELF_ID,
+        "toRoomId": null,
+    })
+}
+
+// 本文に"@sango"があればメンション扱いにする
+pub fn note(id: &str, user: &Value, text: &str) -> Value {
+    let mentions = if text.contains(&format!("@{SELF_ID}")) {
+        vec![SELF_ID]
+    } else {
+        Vec::new()
+    };
+    json!({
+        "id": id,
+        "createdAt": chrono::Utc::now().to_rfc3339(),
+        "text": text,
+        "userId": user["id"],
+        "user": user,
+        "replyId": null,
+        "visibility": "public",
+        "mentions": mentions,
+    })
+}
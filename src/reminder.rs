@@ -0,0 +1,234 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::{
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveTime, TimeDelta, Timelike};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Sango,
+    misskey::{
+        chat::CreateChatMessage,
+        notes::{CreateNote, NoteVisibility},
+    },
+};
+
+// 時間の指定がなかったときにリマインドするまでの時間
+const DEFAULT_DELAY: TimeDelta = TimeDelta::hours(3);
+// 送信に失敗したリマインダーを諦めるまでの時間
+const GIVE_UP_AFTER: TimeDelta = TimeDelta::hours(1);
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    pub note_id: String,
+    pub user_id: String,
+    pub visibility: NoteVisibility,
+    pub due: DateTime<Local>,
+    // チャットで頼まれたものはチャットで送る
+    #[serde(default)]
+    pub chat: bool,
+}
+
+impl Reminder {
+    fn reply(&self, text: &str) -> CreateNote {
+        CreateNote {
+            visibility: Some(self.visibility),
+            reply_id: Some(self.note_id.clone()),
+            text: text.to_owned(),
+            ..Default::default()
+        }
+    }
+}
+
+async fn send(reminder: &Reminder, text: &str, sango: &Sango) -> anyhow::Result<()> {
+    if reminder.chat {
+        sango
+            .client
+            .request(CreateChatMessage::new(&reminder.user_id, text))
+            .await?;
+    } else {
+        sango.client.request(reminder.reply(text)).await?;
+    }
+    Ok(())
+}
+
+// 「2時間後」「1日3時間30分後」など
+static RELATIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:(\d+)\s*日)?\s*(?:(\d+)\s*時間)?\s*(?:(\d+)\s*分)?\s*後").unwrap()
+});
+
+// 「明日の午後3時」「21:00」「9時半」など
+static ABSOLUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?:(明日|あした|明後日|あさって)\s*の?)?\s*(午前|午後)?\s*(\d{1,2})\s*(?:[:：]\s*(\d{2})|時\s*(?:(\d{1,2})\s*分|(半))?)",
+    )
+    .unwrap()
+});
+
+// 本文からリマインドする日時を読み取る
+// 「30分後」「2時間後」「3日後」「明日9時」「21:00に」「9時半」「午後3時」など
+pub fn parse_due(text: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let text = normalize_digits(text);
+
+    for cap in RELATIVE.captures_iter(&text) {
+        let get = |i| {
+            cap.get(i)
+                .map_or(Some(0), |m: regex::Match| m.as_str().parse::<i64>().ok())
+        };
+        let (days, hours, minutes) = (get(1)?, get(2)?, get(3)?);
+        if days == 0 && hours == 0 && minutes == 0 {
+            continue;
+        }
+        let delta = TimeDelta::try_days(days)?
+            .checked_add(&TimeDelta::try_hours(hours)?)?
+            .checked_add(&TimeDelta::try_minutes(minutes)?)?;
+        return now.checked_add_signed(delta);
+    }
+
+    // 「3時間」の「3時」は時刻ではないので飛ばす
+    let cap = ABSOLUTE.captures_iter(&text).find(|cap| {
+        let end = cap.get(0).map_or(0, |m| m.end());
+        cap.get(4).is_some() || !text[end..].starts_with('間')
+    })?;
+    let hour: u32 = cap[3].parse().ok()?;
+    let hour = match cap.get(2).map(|m| m.as_str()) {
+        Some(_) if hour > 12 => return None,
+        Some("午前") => hour % 12,
+        Some(_) => hour % 12 + 12,
+        None => hour,
+    };
+    let minute = match (cap.get(4).or_else(|| cap.get(5)), cap.get(6)) {
+        (Some(m), _) => m.as_str().parse().ok()?,
+        (None, Some(_)) => 30,
+        (None, None) => 0,
+    };
+    let time = NaiveTime::from_hms_opt(hour, minute, 0)?;
+    let days_later = match cap.get(1).map(|m| m.as_str()) {
+        Some("明日" | "あした") => 1,
+        Some(_) => 2,
+        None => 0,
+    };
+    let date = now.date_naive().checked_add_days(Days::new(days_later))?;
+    let due = date.and_time(time).and_local_timezone(Local).earliest()?;
+    if days_later == 0 && due <= now {
+        // 過ぎた時刻なら次の日
+        return due.checked_add_days(Days::new(1));
+    }
+    Some(due)
+}
+
+pub fn default_due(now: DateTime<Local>) -> DateTime<Local> {
+    now + DEFAULT_DELAY
+}
+
+fn normalize_digits(text: &str) -> String {
+    text.chars()
+        .map(|ch| match ch {
+            '０'..='９' => char::from_u32(ch as u32 - '０' as u32 + '0' as u32).unwrap_or(ch),
+            _ => ch,
+        })
+        .collect()
+}
+
+// 「21:00」「明日の9:00」「10月20日 9:00」のような表記
+pub fn format_due(due: DateTime<Local>, now: DateTime<Local>) -> String {
+    let time = format!("{}:{:02}", due.hour(), due.minute());
+    let days = (due.date_naive() - now.date_naive()).num_days();
+    match days {
+        0 => time,
+        1 => format!("明日の{time}"),
+        2 => format!("明後日の{time}"),
+        _ => format!("{}月{}日の{time}", due.month(), due.day()),
+    }
+}
+
+// リマインダーを期限が来たものから順に送る
+// 送るべきものはSaveDataに保存されているので、再起動しても続きから動く
+pub async fn run(sango: Arc<Sango>) {
+    loop {
+        let next = sango.savedata.read().await.next_reminder();
+        let Some(reminder) = next else {
+            sango.reminder_notify.notified().await;
+            continue;
+        };
+
+        let now = Local::now();
+        if reminder.due > now {
+            let wait = (reminder.due - now).to_std().unwrap_or_default();
+            tokio::select! {
+                () = tokio::time::sleep(wait) => {}
+                () = sango.reminder_notify.notified() => continue, // もっと早いものが追加されたかも
+            }
+        }
+
+        if let Err(e) = send(&reminder, "これやった？", &sango).await {
+            log::error!("Failed to send a reminder: {e}");
+            if Local::now() - reminder.due < GIVE_UP_AFTER {
+                tokio::time::sleep(Duration::from_mins(1)).await;
+                continue;
+            }
+            log::warn!("Giving up the reminder for {}.", reminder.note_id);
+        }
+
+        let removed = sango
+            .savedata
+            .write()
+            .await
+            .remove_reminder(&reminder.note_id);
+        if let Err(e) = removed {
+            log::error!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn due(text: &str) -> Option<DateTime<Local>> {
+        let now = Local.with_ymd_and_hms(2025, 1, 1, 10, 0, 0).unwrap();
+        parse_due(text, now)
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> Option<DateTime<Local>> {
+        Local
+            .with_ymd_and_hms(2025, 1, day, hour, minute, 0)
+            .single()
+    }
+
+    #[test]
+    fn relative() {
+        assert_eq!(due("30分後に"), at(1, 10, 30));
+        assert_eq!(due("1日2時間後"), at(2, 12, 0));
+    }
+
+    #[test]
+    fn absolute() {
+        assert_eq!(due("21:00に"), at(1, 21, 0));
+        assert_eq!(due("9時半"), at(2, 9, 30));
+        assert_eq!(due("明日の9時"), at(2, 9, 0));
+    }
+
+    #[test]
+    fn am_pm() {
+        assert_eq!(due("午後3時に"), at(1, 15, 0));
+        assert_eq!(due("午前11時"), at(1, 11, 0));
+        assert_eq!(due("明日の午後12時半"), at(2, 12, 30));
+        assert_eq!(due("午後13時"), None);
+    }
+
+    #[test]
+    fn hours_are_not_time() {
+        assert_eq!(due("3時間以内に"), None);
+        assert_eq!(due("2時間以内に、5時に起こして"), at(2, 5, 0));
+    }
+}
@@ -0,0 +1,108 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use serde::Deserialize;
+
+use crate::{config::Config, misskey::users::User};
+
+// 上の役割ほど強く、下の役割でできることは全部できる
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Everyone,
+    Trusted,
+    Moderator,
+    Admin,
+}
+
+// config.tomlの[roles]。ユーザーIDか"@user@host"(ローカルなら"@user"でもいい)で書く
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RolesConfig {
+    pub admin: Vec<String>,
+    pub moderator: Vec<String>,
+    pub trusted: Vec<String>,
+}
+
+pub struct Roles {
+    config: RolesConfig,
+    local_host: String,
+}
+
+impl Roles {
+    // config.tomlのadminもadminの役割に含める
+    pub fn new(mut config: RolesConfig, admin: &str, local_host: &str) -> Self {
+        config.admin.push(admin.to_owned());
+        let local_host = local_host
+            .strip_prefix("https://")
+            .or_else(|| local_host.strip_prefix("http://"))
+            .unwrap_or(local_host)
+            .to_lowercase();
+        Self { config, local_host }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.roles.clone(), &config.admin, &config.host)
+    }
+
+    pub fn role_of(&self, user: &User) -> Role {
+        let matches = |list: &[String]| list.iter().any(|entry| self.is_user(entry, user));
+        if matches(&self.config.admin) {
+            Role::Admin
+        } else if matches(&self.config.moderator) {
+            Role::Moderator
+        } else if matches(&self.config.trusted) {
+            Role::Trusted
+        } else {
+            Role::Everyone
+        }
+    }
+
+    pub fn has(&self, user: &User, role: Role) -> bool {
+        self.role_of(user) >= role
+    }
+
+    fn is_user(&self, entry: &str, user: &User) -> bool {
+        let Some(acct) = entry.strip_prefix('@') else {
+            return entry == user.id;
+        };
+        let (username, host) = acct.split_once('@').unwrap_or((acct, &self.local_host));
+        let user_host = user.host.as_deref().unwrap_or(&self.local_host);
+        username.eq_ignore_ascii_case(&user.username) && host.eq_ignore_ascii_case(user_host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    fn user(id: &str, username: &str, host: Option<&str>) -> User {
+        let mut user = mock::user(id, username, false);
+        user["host"] = host.into();
+        serde_json::from_value(user).unwrap()
+    }
+
+    #[test]
+    fn role_of() {
+        let config = RolesConfig {
+            admin: Vec::new(),
+            moderator: vec!["@bob".to_owned()],
+            trusted: vec!["@carol@example.net".to_owned()],
+        };
+        let roles = Roles::new(config, "alice-id", "https://example.com");
+
+        assert_eq!(roles.role_of(&user("alice-id", "alice", None)), Role::Admin);
+        assert_eq!(roles.role_of(&user("bob-id", "bob", None)), Role::Moderator);
+        assert_eq!(
+            roles.role_of(&user("bob-id", "bob", Some("example.net"))),
+            Role::Everyone
+        );
+        assert_eq!(
+            roles.role_of(&user("carol-id", "Carol", Some("example.net"))),
+            Role::Trusted
+        );
+        assert!(roles.has(&user("alice-id", "alice", None), Role::Trusted));
+        assert!(!roles.has(&user("dave-id", "dave", None), Role::Trusted));
+    }
+}
@@ -0,0 +1,119 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::path::Path;
+
+use anyhow::Context;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, de::Error};
+
+// rules.tomlが無いときに使う
+pub const DEFAULT_RULES: &str = include_str!("../rules_example.toml");
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    #[serde(default)]
+    pub mention: Vec<Rule>,
+    #[serde(default)]
+    pub note: Vec<Rule>,
+}
+
+// priorityを省略したときの優先度
+pub const DEFAULT_PRIORITY: i32 = 50;
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    // config.tomlのhandlersで使う名前。省略時は最初のキーワード
+    #[serde(default)]
+    pub name: Option<String>,
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    // これに当てはまる場合も反応しない(正規表現)。読み込むときにコンパイルしておく
+    #[serde(default, deserialize_with = "regex")]
+    pub exclude_pattern: Option<Regex>,
+    #[serde(default)]
+    pub responses: Vec<String>,
+    // 返信の代わりに、または返信と一緒にする絵文字リアクション
+    #[serde(default)]
+    pub reaction: Option<String>,
+    #[serde(default = "default_probability")]
+    pub probability: f64,
+    #[serde(default)]
+    pub reply: ReplyCondition,
+    // 同じpriorityならrules.tomlに書いた順になる
+    #[serde(default = "default_priority")]
+    pub priority: i32,
+}
+
+const fn default_probability() -> f64 {
+    1.0
+}
+
+const fn default_priority() -> i32 {
+    DEFAULT_PRIORITY
+}
+
+fn regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Regex>, D::Error> {
+    let Some(pattern) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    Regex::new(&pattern)
+        .map(Some)
+        .map_err(|e| D::Error::custom(format!("invalid exclude_pattern: {e}")))
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplyCondition {
+    #[default]
+    Any,
+    Only,  // リプライのみ
+    Never, // リプライ以外のみ
+}
+
+impl Rules {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = match std::fs::read_to_string(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                log::warn!("{} is not found; Using default rules...", path.display());
+                DEFAULT_RULES.to_owned()
+            }
+            Err(e) => return Err(e).context("Failed to load rules"),
+        };
+        Self::parse(&file)
+    }
+
+    pub fn parse(file: &str) -> anyhow::Result<Self> {
+        let rules: Self = toml::from_str(file).context("Failed to parse rules")?;
+        for rule in rules.mention.iter().chain(&rules.note) {
+            rule.validate()?;
+        }
+        Ok(rules)
+    }
+}
+
+impl Rule {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.keywords[0])
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.keywords.is_empty(), "Rule has no keywords");
+        anyhow::ensure!(
+            !self.responses.is_empty() || self.reaction.is_some(),
+            "Rule for {:?} has neither responses nor reaction",
+            self.keywords
+        );
+        anyhow::ensure!(
+            (0.0..=1.0).contains(&self.probability),
+            "Rule for {:?} has invalid probability",
+            self.keywords
+        );
+        Ok(())
+    }
+}
@@ -2,46 +2,130 @@
 //
 // SPDX-License-Identifier: UPL-1.0
 
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
 use anyhow::Context;
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
 
-use crate::misskey::users::User;
+use crate::{
+    misskey::users::User,
+    reminder::Reminder,
+    savedata::{json::JsonStorage, sqlite::SqliteStorage},
+};
 
-#[derive(Serialize, Deserialize, Default)]
-pub struct SaveData {
-    nicknames: HashMap<String, String>,
+mod json;
+#[cfg(test)]
+mod memory;
+mod sqlite;
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Backend {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+// 保存先ごとの実装
+pub trait Storage: Send + Sync {
+    fn nicknames(&self) -> anyhow::Result<Vec<(String, String)>>;
+    fn get_nickname(&self, id: &str) -> anyhow::Result<Option<String>>;
+    fn store_nickname(&mut self, id: &str, nick: &str) -> anyhow::Result<()>;
+    fn forget_nickname(&mut self, id: &str) -> anyhow::Result<bool>;
+
+    fn reminders(&self) -> anyhow::Result<Vec<Reminder>>;
+    fn add_reminder(&mut self, reminder: &Reminder) -> anyhow::Result<()>;
+    fn remove_reminder(&mut self, note_id: &str) -> anyhow::Result<bool>;
+
+    // BOT自身の状態など、その他の値
+    fn values(&self) -> anyhow::Result<Vec<(String, String)>>;
+    fn get_value(&self, key: &str) -> anyhow::Result<Option<String>>;
+    fn set_value(&mut self, key: &str, value: &str) -> anyhow::Result<()>;
 }
 
+const LAST_WAKEUP: &str = "lastWakeup";
+const LAST_MENTION_ID: &str = "lastMentionId";
+const COOLDOWNS: &str = "cooldowns";
+const MUTED: &str = "muted";
+const OPTED_OUT: &str = "optedOut";
+
+pub struct SaveData(Box<dyn Storage>);
+
 impl SaveData {
-    pub fn load() -> anyhow::Result<Self> {
-        let file = std::fs::read_to_string("savedata.json").context("Failed to load savedata")?;
-        let savedata = serde_json::from_str(&file).context("Failed to parse savedata")?;
-        Ok(savedata)
+    pub fn open(backend: Backend, data_dir: &Path) -> anyhow::Result<Self> {
+        let storage: Box<dyn Storage> = match backend {
+            Backend::Json => Box::new(JsonStorage::open(&data_dir.join(json::FILE_NAME))?),
+            Backend::Sqlite => Box::new(SqliteStorage::open(&data_dir.join(sqlite::FILE_NAME))?),
+        };
+        Ok(Self(storage))
+    }
+
+    // 読むだけのときに使う。ファイルが無くても作らず、古い形式でも書き換えない
+    pub fn open_read_only(backend: Backend, data_dir: &Path) -> anyhow::Result<Self> {
+        let path = match backend {
+            Backend::Json => data_dir.join(json::FILE_NAME),
+            Backend::Sqlite => data_dir.join(sqlite::FILE_NAME),
+        };
+        anyhow::ensure!(
+            path.try_exists().context("Failed to load savedata")?,
+            "{} is not found",
+            path.display()
+        );
+        let storage: Box<dyn Storage> = match backend {
+            Backend::Json => Box::new(JsonStorage::read(&path)?.0),
+            Backend::Sqlite => Box::new(SqliteStorage::open_read_only(&path)?),
+        };
+        Ok(Self(storage))
     }
 
-    pub fn save(&self) -> anyhow::Result<()> {
-        let mut file =
-            std::fs::File::create("savedata.json").context("Failed to open file for writing")?;
-        serde_json::to_writer_pretty(&mut file, self).context("Failed to write savedata")?;
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self(Box::new(memory::MemoryStorage::default()))
+    }
+
+    // data_dirのsavedata.jsonの中身を全部取り込む
+    pub fn import_json(&mut self, data_dir: &Path) -> anyhow::Result<()> {
+        let from = JsonStorage::load(&data_dir.join(json::FILE_NAME))?;
+        let nicknames = from.nicknames()?;
+        let reminders = from.reminders()?;
+        for (id, nick) in &nicknames {
+            self.0.store_nickname(id, nick)?;
+        }
+        for reminder in &reminders {
+            self.0.add_reminder(reminder)?;
+        }
+        for (key, value) in from.values()? {
+            self.0.set_value(&key, &value)?;
+        }
+        log::info!(
+            "Imported {} nicknames and {} reminders.",
+            nicknames.len(),
+            reminders.len()
+        );
         Ok(())
     }
 
     pub fn store_nickname(&mut self, id: &str, nick: &str) -> anyhow::Result<()> {
-        self.nicknames.insert(id.to_owned(), nick.to_owned());
-        self.save()?;
-        Ok(())
+        self.0.store_nickname(id, nick)
     }
 
     pub fn forget_nickname(&mut self, id: &str) -> anyhow::Result<bool> {
-        let res = self.nicknames.remove(id);
-        self.save()?;
-        Ok(res.is_some())
+        self.0.forget_nickname(id)
+    }
+
+    pub fn nicknames(&self) -> anyhow::Result<Vec<(String, String)>> {
+        self.0.nicknames()
     }
 
     pub fn get_nickname(&self, id: &str) -> Option<String> {
-        self.nicknames.get(id).cloned()
+        self.0.get_nickname(id).unwrap_or_else(|e| {
+            log::error!("{e}");
+            None
+        })
     }
 
     pub fn get_displayname(&self, user: &User) -> String {
@@ -49,4 +133,130 @@ impl SaveData {
             .or_else(|| user.name.clone())
             .unwrap_or_else(|| user.username.clone())
     }
+
+    pub fn add_reminder(&mut self, reminder: &Reminder) -> anyhow::Result<()> {
+        self.0.add_reminder(reminder)
+    }
+
+    pub fn remove_reminder(&mut self, note_id: &str) -> anyhow::Result<bool> {
+        self.0.remove_reminder(note_id)
+    }
+
+    // 期限が一番近いリマインダー
+    pub fn next_reminder(&self) -> Option<Reminder> {
+        let reminders = self.0.reminders().unwrap_or_else(|e| {
+            log::error!("{e}");
+            Vec::new()
+        });
+        reminders.into_iter().min_by_key(|reminder| reminder.due)
+    }
+
+    // 最後に起きたことを投稿した日時
+    pub fn last_wakeup(&self) -> Option<DateTime<Local>> {
+        let value = self.0.get_value(LAST_WAKEUP).unwrap_or_else(|e| {
+            log::error!("{e}");
+            None
+        })?;
+        DateTime::parse_from_rfc3339(&value)
+            .ok()
+            .map(|date| date.with_timezone(&Local))
+    }
+
+    pub fn set_last_wakeup(&mut self, date: DateTime<Local>) -> anyhow::Result<()> {
+        self.0.set_value(LAST_WAKEUP, &date.to_rfc3339())
+    }
+
+    // 最後に処理したメンションのID
+    pub fn last_mention_id(&self) -> Option<String> {
+        self.0.get_value(LAST_MENTION_ID).unwrap_or_else(|e| {
+            log::error!("{e}");
+            None
+        })
+    }
+
+    // IDは時系列順に並ぶので、今より新しいときだけ更新する
+    pub fn update_last_mention_id(&mut self, id: &str) -> anyhow::Result<()> {
+        if self.last_mention_id().is_none_or(|last| last.as_str() < id) {
+            self.0.set_value(LAST_MENTION_ID, id)?;
+        }
+        Ok(())
+    }
+
+    // タイムラインへの反応のクールダウン。JSONにして1つの値として保存する
+    pub fn cooldowns(&self) -> HashMap<String, DateTime<Local>> {
+        let value = self.0.get_value(COOLDOWNS).unwrap_or_else(|e| {
+            log::error!("{e}");
+            None
+        });
+        value
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn set_cooldowns(
+        &mut self,
+        cooldowns: &HashMap<String, DateTime<Local>>,
+    ) -> anyhow::Result<()> {
+        self.0
+            .set_value(COOLDOWNS, &serde_json::to_string(cooldowns)?)
+    }
+
+    // 管理者コマンドでタイムラインへの反応を止めているか
+    pub fn muted(&self) -> bool {
+        let value = self.0.get_value(MUTED).unwrap_or_else(|e| {
+            log::error!("{e}");
+            None
+        });
+        value.is_some_and(|value| value == "true")
+    }
+
+    pub fn set_muted(&mut self, muted: bool) -> anyhow::Result<()> {
+        self.0.set_value(MUTED, &muted.to_string())
+    }
+
+    // タイムラインに反応しないでほしいと言われたユーザーのID。JSONにして1つの値として保存する
+    pub fn opted_out(&self) -> HashSet<String> {
+        let value = self.0.get_value(OPTED_OUT).unwrap_or_else(|e| {
+            log::error!("{e}");
+            None
+        });
+        value
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn set_opted_out(&mut self, opted_out: &HashSet<String>) -> anyhow::Result<()> {
+        self.0
+            .set_value(OPTED_OUT, &serde_json::to_string(opted_out)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only() {
+        let dir = std::env::temp_dir().join(format!("sango_chan_read_only_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 無くても作らない
+        assert!(SaveData::open_read_only(Backend::Json, &dir).is_err());
+        assert!(SaveData::open_read_only(Backend::Sqlite, &dir).is_err());
+        assert!(!dir.join(json::FILE_NAME).exists());
+        assert!(!dir.join(sqlite::FILE_NAME).exists());
+
+        // 古い形式でも書き換えない
+        let old = r#"{"nicknames": {"alice": "アリス"}}"#;
+        std::fs::write(dir.join(json::FILE_NAME), old).unwrap();
+        let savedata = SaveData::open_read_only(Backend::Json, &dir).unwrap();
+        assert_eq!(savedata.get_nickname("alice").as_deref(), Some("アリス"));
+        assert_eq!(
+            std::fs::read_to_string(dir.join(json::FILE_NAME)).unwrap(),
+            old
+        );
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
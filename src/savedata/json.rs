@@ -0,0 +1,197 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{reminder::Reminder, savedata::Storage};
+
+pub const FILE_NAME: &str = "savedata.json";
+
+// 保存形式を変えたらここを増やして、MIGRATIONSに変換を足す
+const CURRENT_VERSION: usize = 2;
+
+// MIGRATIONS[n]はバージョンnのデータをバージョンn+1に変換する
+const MIGRATIONS: &[fn(&mut Value) -> anyhow::Result<()>] = &[migrate_v0_to_v1, migrate_v1_to_v2];
+
+// v0: バージョン番号なし
+// v1: バージョン番号とリマインダーを追加
+fn migrate_v0_to_v1(data: &mut Value) -> anyhow::Result<()> {
+    let data = data.as_object_mut().context("savedata is not an object")?;
+    data.entry("reminders").or_insert_with(|| json!([]));
+    Ok(())
+}
+
+// v2: BOTの状態を覚えておくための値を追加
+fn migrate_v1_to_v2(data: &mut Value) -> anyhow::Result<()> {
+    let data = data.as_object_mut().context("savedata is not an object")?;
+    data.entry("values").or_insert_with(|| json!({}));
+    Ok(())
+}
+
+// 全部メモリに持っておいて、変更のたびにファイル全体を書き直す
+#[derive(Serialize, Deserialize, Default)]
+pub struct JsonStorage {
+    version: usize,
+    nicknames: HashMap<String, String>,
+    reminders: Vec<Reminder>,
+    values: HashMap<String, String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl JsonStorage {
+    fn new(path: &Path) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            path: path.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    // ファイルが無ければ新しく作る
+    // 読めないファイルを上書きしてしまわないように、壊れている場合はエラーにする
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if path.try_exists().context("Failed to load savedata")? {
+            Self::load(path)
+        } else {
+            log::warn!("{} is not found; Creating new one...", path.display());
+            let savedata = Self::new(path);
+            savedata.save()?;
+            Ok(savedata)
+        }
+    }
+
+    // 古い形式なら変換して、変換前のファイルを残してから書き直す
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let (savedata, old_version) = Self::read(path)?;
+        if let Some(version) = old_version {
+            // 念のため変換前のファイルを残しておく
+            let backup = with_suffix(path, &format!(".v{version}.bak"));
+            std::fs::copy(path, &backup).context("Failed to back up savedata")?;
+            savedata.save()?;
+            log::info!("Migrated savedata from v{version} to v{CURRENT_VERSION}.");
+        }
+        Ok(savedata)
+    }
+
+    // ファイルには触らずに読む。古い形式ならメモリ上だけで変換して、元のバージョンも返す
+    pub fn read(path: &Path) -> anyhow::Result<(Self, Option<usize>)> {
+        let file = std::fs::read_to_string(path).context("Failed to load savedata")?;
+        let mut data: Value = serde_json::from_str(&file).context("Failed to parse savedata")?;
+
+        let version = data.get("version").map_or(Some(0), Value::as_u64);
+        let version = version
+            .and_then(|version| usize::try_from(version).ok())
+            .context("Invalid savedata version")?;
+        anyhow::ensure!(
+            version <= CURRENT_VERSION,
+            "savedata version {version} is newer than supported ({CURRENT_VERSION})"
+        );
+
+        let migrated = version < CURRENT_VERSION;
+        if migrated {
+            for (from, migrate) in MIGRATIONS.iter().enumerate().skip(version) {
+                migrate(&mut data)
+                    .with_context(|| format!("Failed to migrate savedata from v{from}"))?;
+            }
+            data["version"] = json!(CURRENT_VERSION);
+        }
+
+        let savedata = serde_json::from_value(data).context("Failed to parse savedata")?;
+        let savedata = Self {
+            path: path.to_owned(),
+            ..savedata
+        };
+        Ok((savedata, migrated.then_some(version)))
+    }
+
+    // 一時ファイルに書いてから置き換えるので、途中で落ちても元のファイルは壊れない
+    fn save(&self) -> anyhow::Result<()> {
+        let tmp = with_suffix(&self.path, ".tmp");
+        let mut file = std::fs::File::create(&tmp).context("Failed to open file for writing")?;
+        serde_json::to_writer_pretty(&mut file, self).context("Failed to write savedata")?;
+        file.flush().context("Failed to write savedata")?;
+        file.sync_all().context("Failed to write savedata")?;
+        std::fs::rename(&tmp, &self.path).context("Failed to replace savedata")?;
+        Ok(())
+    }
+}
+
+impl Storage for JsonStorage {
+    fn nicknames(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let nicknames = self
+            .nicknames
+            .iter()
+            .map(|(id, nick)| (id.clone(), nick.clone()))
+            .collect();
+        Ok(nicknames)
+    }
+
+    fn get_nickname(&self, id: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.nicknames.get(id).cloned())
+    }
+
+    fn store_nickname(&mut self, id: &str, nick: &str) -> anyhow::Result<()> {
+        self.nicknames.insert(id.to_owned(), nick.to_owned());
+        self.save()?;
+        Ok(())
+    }
+
+    fn forget_nickname(&mut self, id: &str) -> anyhow::Result<bool> {
+        let res = self.nicknames.remove(id);
+        self.save()?;
+        Ok(res.is_some())
+    }
+
+    fn reminders(&self) -> anyhow::Result<Vec<Reminder>> {
+        Ok(self.reminders.clone())
+    }
+
+    fn add_reminder(&mut self, reminder: &Reminder) -> anyhow::Result<()> {
+        self.reminders.push(reminder.clone());
+        self.save()?;
+        Ok(())
+    }
+
+    fn remove_reminder(&mut self, note_id: &str) -> anyhow::Result<bool> {
+        let len = self.reminders.len();
+        self.reminders
+            .retain(|reminder| reminder.note_id != note_id);
+        self.save()?;
+        Ok(self.reminders.len() != len)
+    }
+
+    fn values(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let values = self
+            .values
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Ok(values)
+    }
+
+    fn get_value(&self, key: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.values.get(key).cloned())
+    }
+
+    fn set_value(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.values.insert(key.to_owned(), value.to_owned());
+        self.save()?;
+        Ok(())
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
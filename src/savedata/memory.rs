@@ -0,0 +1,63 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::collections::HashMap;
+
+use crate::{reminder::Reminder, savedata::Storage};
+
+// テスト用。どこにも保存しない
+#[derive(Default)]
+pub struct MemoryStorage {
+    nicknames: HashMap<String, String>,
+    reminders: Vec<Reminder>,
+    values: HashMap<String, String>,
+}
+
+impl Storage for MemoryStorage {
+    fn nicknames(&self) -> anyhow::Result<Vec<(String, String)>> {
+        Ok(self.nicknames.clone().into_iter().collect())
+    }
+
+    fn get_nickname(&self, id: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.nicknames.get(id).cloned())
+    }
+
+    fn store_nickname(&mut self, id: &str, nick: &str) -> anyhow::Result<()> {
+        self.nicknames.insert(id.to_owned(), nick.to_owned());
+        Ok(())
+    }
+
+    fn forget_nickname(&mut self, id: &str) -> anyhow::Result<bool> {
+        Ok(self.nicknames.remove(id).is_some())
+    }
+
+    fn reminders(&self) -> anyhow::Result<Vec<Reminder>> {
+        Ok(self.reminders.clone())
+    }
+
+    fn add_reminder(&mut self, reminder: &Reminder) -> anyhow::Result<()> {
+        self.reminders.push(reminder.clone());
+        Ok(())
+    }
+
+    fn remove_reminder(&mut self, note_id: &str) -> anyhow::Result<bool> {
+        let before = self.reminders.len();
+        self.reminders
+            .retain(|reminder| reminder.note_id != note_id);
+        Ok(self.reminders.len() != before)
+    }
+
+    fn values(&self) -> anyhow::Result<Vec<(String, String)>> {
+        Ok(self.values.clone().into_iter().collect())
+    }
+
+    fn get_value(&self, key: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.values.get(key).cloned())
+    }
+
+    fn set_value(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.values.insert(key.to_owned(), value.to_owned());
+        Ok(())
+    }
+}
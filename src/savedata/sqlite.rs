@@ -0,0 +1,229 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use std::{path::Path, sync::Mutex};
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+
+use crate::{misskey::notes::NoteVisibility, reminder::Reminder, savedata::Storage};
+
+pub const FILE_NAME: &str = "savedata.sqlite3";
+
+// MIGRATIONS[n]はuser_version nのデータベースをn+1にする
+const MIGRATIONS: &[&str] = &[
+    // v1: 呼び名とリマインダー
+    "CREATE TABLE nicknames (
+        user_id TEXT PRIMARY KEY,
+        nickname TEXT NOT NULL
+    );
+    CREATE TABLE reminders (
+        note_id TEXT PRIMARY KEY,
+        user_id TEXT NOT NULL,
+        visibility TEXT NOT NULL,
+        due TEXT NOT NULL
+    );",
+    // v2: BOTの状態を覚えておくための値
+    "CREATE TABLE vals (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    // v3: チャットで頼まれたリマインダー
+    "ALTER TABLE reminders ADD COLUMN chat INTEGER NOT NULL DEFAULT 0;",
+];
+
+// 変更のたびにその行だけを書き換える
+pub struct SqliteStorage {
+    // ConnectionはSyncではないのでMutexに入れる
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut conn = Connection::open(path).context("Failed to open database")?;
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    // ファイルを作ったり変換したりせずに開く。読むだけのときに使う
+    pub fn open_read_only(path: &Path) -> anyhow::Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .context("Failed to open database")?;
+        let version = user_version(&conn)?;
+        anyhow::ensure!(
+            version == MIGRATIONS.len(),
+            "Database version {version} is not the current one ({}); Run the bot once to migrate it",
+            MIGRATIONS.len()
+        );
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // 書き込み中にパニックしてもトランザクションで守られているので、そのまま使う
+        self.conn
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+fn user_version(conn: &Connection) -> anyhow::Result<usize> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .context("Failed to read database version")
+}
+
+fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    let version = user_version(conn)?;
+    anyhow::ensure!(
+        version <= MIGRATIONS.len(),
+        "Database version {version} is newer than supported ({})",
+        MIGRATIONS.len()
+    );
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)
+            .with_context(|| format!("Failed to migrate database from v{from}"))?;
+        tx.pragma_update(None, "user_version", from + 1)?;
+        tx.commit()?;
+        log::info!("Migrated database to v{}.", from + 1);
+    }
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn nicknames(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT user_id, nickname FROM nicknames")?;
+        let nicknames = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        drop(stmt);
+        drop(conn);
+        Ok(nicknames)
+    }
+
+    fn get_nickname(&self, id: &str) -> anyhow::Result<Option<String>> {
+        let nickname = self
+            .conn()
+            .query_row(
+                "SELECT nickname FROM nicknames WHERE user_id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(nickname)
+    }
+
+    fn store_nickname(&mut self, id: &str, nick: &str) -> anyhow::Result<()> {
+        self.conn().execute(
+            "INSERT INTO nicknames (user_id, nickname) VALUES (?1, ?2)
+            ON CONFLICT (user_id) DO UPDATE SET nickname = excluded.nickname",
+            params![id, nick],
+        )?;
+        Ok(())
+    }
+
+    fn forget_nickname(&mut self, id: &str) -> anyhow::Result<bool> {
+        let removed = self
+            .conn()
+            .execute("DELETE FROM nicknames WHERE user_id = ?1", params![id])?;
+        Ok(removed > 0)
+    }
+
+    fn reminders(&self) -> anyhow::Result<Vec<Reminder>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT note_id, user_id, visibility, due, chat FROM reminders")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, bool>(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(stmt);
+        drop(conn);
+        rows.into_iter()
+            .map(|(note_id, user_id, visibility, due, chat)| {
+                let visibility: NoteVisibility =
+                    serde_json::from_value(serde_json::Value::String(visibility))
+                        .context("Invalid visibility in database")?;
+                let due = DateTime::parse_from_rfc3339(&due)
+                    .context("Invalid date in database")?
+                    .with_timezone(&Local);
+                Ok(Reminder {
+                    note_id,
+                    user_id,
+                    visibility,
+                    due,
+                    chat,
+                })
+            })
+            .collect()
+    }
+
+    fn add_reminder(&mut self, reminder: &Reminder) -> anyhow::Result<()> {
+        let visibility = serde_json::to_value(reminder.visibility)?;
+        let visibility = visibility.as_str().unwrap_or_default();
+        self.conn().execute(
+            "INSERT OR REPLACE INTO reminders (note_id, user_id, visibility, due, chat)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                reminder.note_id,
+                reminder.user_id,
+                visibility,
+                reminder.due.to_rfc3339(),
+                reminder.chat
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn remove_reminder(&mut self, note_id: &str) -> anyhow::Result<bool> {
+        let removed = self
+            .conn()
+            .execute("DELETE FROM reminders WHERE note_id = ?1", params![note_id])?;
+        Ok(removed > 0)
+    }
+
+    fn values(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT key, value FROM vals")?;
+        let values = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        drop(stmt);
+        drop(conn);
+        Ok(values)
+    }
+
+    fn get_value(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let value = self
+            .conn()
+            .query_row(
+                "SELECT value FROM vals WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    fn set_value(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.conn().execute(
+            "INSERT INTO vals (key, value) VALUES (?1, ?2)
+            ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+// 決まった時刻に投稿する(朝の挨拶や時報など)
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime, TimeDelta, Timelike};
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
+
+use crate::{
+    Sango,
+    misskey::notes::{CreateNote, NoteVisibility},
+};
+
+// 次の投稿時刻を探すのを諦めるまでの日数(2月29日だけのものも見つかるように)
+const SEARCH_DAYS: u64 = 366 * 4 + 1;
+
+// config.tomlの[[schedule]]
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "ScheduleEntry")]
+pub struct ScheduledPost {
+    cron: Cron,
+    texts: Vec<String>,
+    visibility: NoteVisibility,
+    local_only: bool,
+}
+
+// config.tomlに書く形。cronか、at(とdays)のどちらかを書く
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleEntry {
+    cron: Option<String>, // "分 時 日 月 曜日"
+    at: Option<String>,   // "7:00"
+    days: Option<String>, // "weekdays"、"weekends"、"mon,wed,fri"など。省略時は毎日
+    texts: Vec<String>,   // この中からランダムに1つ投稿する
+    #[serde(default = "default_visibility")]
+    visibility: NoteVisibility,
+    #[serde(default)]
+    local_only: bool,
+}
+
+const fn default_visibility() -> NoteVisibility {
+    NoteVisibility::Home
+}
+
+impl TryFrom<ScheduleEntry> for ScheduledPost {
+    type Error = String;
+
+    fn try_from(entry: ScheduleEntry) -> Result<Self, Self::Error> {
+        if entry.texts.is_empty() {
+            return Err("schedule needs at least one text".to_owned());
+        }
+        let cron = match (entry.cron, entry.at) {
+            (Some(cron), None) if entry.days.is_none() => Cron::parse(&cron)?,
+            (None, Some(at)) => Cron::daily(&at, entry.days.as_deref())?,
+            _ => return Err("schedule needs either cron or at (with optional days)".to_owned()),
+        };
+        Ok(Self {
+            cron,
+            texts: entry.texts,
+            visibility: entry.visibility,
+            local_only: entry.local_only,
+        })
+    }
+}
+
+// 各欄で当てはまる値をビットで持つ
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Cron {
+    minutes: u64,  // 0-59
+    hours: u32,    // 0-23
+    days: u32,     // 1-31
+    months: u16,   // 1-12
+    weekdays: u8,  // 0-6(日曜が0)
+    any_day: bool, // 日が"*"
+    any_weekday: bool,
+}
+
+impl Cron {
+    fn parse(cron: &str) -> Result<Self, String> {
+        let fields: Vec<_> = cron.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("cron must have 5 fields: {cron}"));
+        };
+        let field = |text, min, max| {
+            parse_field(text, min, max)
+                .ok_or_else(|| format!("invalid cron field \"{text}\": {cron}"))
+        };
+        // 7も日曜として受け付ける
+        let weekday_bits = field(weekdays, 0, 7)?;
+        let weekday_bits = (weekday_bits | weekday_bits >> 7) & 0b111_1111;
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(Self {
+            minutes: field(minutes, 0, 59)?,
+            hours: field(hours, 0, 23)? as u32,
+            days: field(days, 1, 31)? as u32,
+            months: field(months, 1, 12)? as u16,
+            weekdays: weekday_bits as u8,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+
+    // "7:00"と"weekdays"のような書き方
+    fn daily(at: &str, days: Option<&str>) -> Result<Self, String> {
+        let time = NaiveTime::parse_from_str(at.trim(), "%H:%M")
+            .map_err(|_| format!("at must be like \"7:00\": {at}"))?;
+        let weekdays = match days.map(str::trim) {
+            None | Some("everyday") => "*".to_owned(),
+            Some("weekdays") => "1-5".to_owned(),
+            Some("weekends") => "0,6".to_owned(),
+            Some(days) => days
+                .split(',')
+                .map(|day| {
+                    weekday_number(day.trim())
+                        .map(|number| number.to_string())
+                        .ok_or_else(|| format!("unknown day: {day}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .join(","),
+        };
+        Self::parse(&format!("{} {} * * {weekdays}", time.minute(), time.hour()))
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        // cronと同じく、日と曜日の両方が指定されていたらどちらかに当てはまればいい
+        let day_check = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        self.months & (1 << date.month()) != 0 && day_check
+    }
+
+    // nowより後で、最初に当てはまる時刻
+    fn next_after(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        // 次の分の0秒から探す
+        let start = (now + TimeDelta::minutes(1))
+            .with_second(0)?
+            .with_nanosecond(0)?;
+        let start_date = start.date_naive();
+        for days_later in 0..SEARCH_DAYS {
+            let date = start_date.checked_add_days(Days::new(days_later))?;
+            if !self.matches_date(date) {
+                continue;
+            }
+            for hour in 0..24 {
+                if self.hours & (1 << hour) == 0 {
+                    continue;
+                }
+                for minute in 0..60 {
+                    if self.minutes & (1 << minute) == 0 {
+                        continue;
+                    }
+                    let Some(time) = NaiveTime::from_hms_opt(hour, minute, 0) else {
+                        continue;
+                    };
+                    // 夏時間で存在しない時刻は飛ばす
+                    let Some(at) = date.and_time(time).and_local_timezone(Local).earliest() else {
+                        continue;
+                    };
+                    if at >= start {
+                        return Some(at);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+// "*"、"5"、"1-5"、"*/15"、"0-30/10"、"1,3,5"
+fn parse_field(text: &str, min: u32, max: u32) -> Option<u64> {
+    let mut bits = 0;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().ok().filter(|step| *step > 0)?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse().ok()?, end.parse().ok()?)
+        } else {
+            let value = range.parse().ok()?;
+            (value, value)
+        };
+        if start < min || end > max || start > end {
+            return None;
+        }
+        for value in (start..=end).step_by(step) {
+            bits |= 1 << value;
+        }
+    }
+    Some(bits)
+}
+
+// "sun"や"Monday"を曜日の番号(日曜が0)にする
+fn weekday_number(day: &str) -> Option<usize> {
+    let days = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+    let day = day.to_lowercase();
+    days.iter().position(|name| day.starts_with(name))
+}
+
+impl ScheduledPost {
+    fn note(&self) -> Option<CreateNote> {
+        let text = self.texts.choose(&mut rand::rng())?;
+        Some(CreateNote {
+            visibility: Some(self.visibility),
+            local_only: self.local_only.then_some(true),
+            text: text.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+// 投稿ごとに、時刻が来るのを待って投稿するのを繰り返す
+// 接続とは関係なく動くので、再接続中でも投稿する
+pub fn spawn(sango: &Arc<Sango>, posts: Vec<ScheduledPost>) {
+    for post in posts {
+        tokio::spawn(run(Arc::clone(sango), post));
+    }
+}
+
+async fn run(sango: Arc<Sango>, post: ScheduledPost) {
+    let mut last = Local::now();
+    loop {
+        // 時計のずれで少し早く起きても、同じ時刻に2回投稿しないように前回の時刻より後を探す
+        let now = Local::now();
+        let Some(next) = post.cron.next_after(now.max(last)) else {
+            log::warn!("A scheduled post will never be posted.");
+            return;
+        };
+        last = next;
+        let wait = (next - now).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        let Some(note) = post.note() else {
+            return;
+        };
+        match sango.client.request(note).await {
+            Ok(_) => log::info!("Posted a scheduled note."),
+            Err(e) => log::error!("Failed to post a scheduled note: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        // 2025年1月1日は水曜日
+        Local
+            .with_ymd_and_hms(2025, 1, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_field("*/15", 0, 59),
+            Some(0b1 | 1 << 15 | 1 << 30 | 1 << 45)
+        );
+        assert_eq!(parse_field("1-3,5", 0, 6), Some(0b10_1110));
+        assert_eq!(parse_field("60", 0, 59), None);
+        assert_eq!(parse_field("*/0", 0, 59), None);
+        assert!(Cron::parse("0 7 * *").is_err());
+        assert_eq!(
+            Cron::daily("7:00", Some("weekdays")).unwrap(),
+            Cron::parse("0 7 * * 1-5").unwrap()
+        );
+        assert_eq!(
+            Cron::daily("21:30", Some("sun, sat")).unwrap(),
+            Cron::parse("30 21 * * 0,6").unwrap()
+        );
+        assert!(Cron::daily("7時", None).is_err());
+    }
+
+    #[test]
+    fn next_after() {
+        let hourly = Cron::parse("0 * * * *").unwrap();
+        assert_eq!(hourly.next_after(at(1, 12, 0)), Some(at(1, 13, 0)));
+        assert_eq!(hourly.next_after(at(1, 23, 59)), Some(at(2, 0, 0)));
+        let almost = at(1, 12, 59) + TimeDelta::milliseconds(999);
+        assert_eq!(hourly.next_after(almost), Some(at(1, 13, 0)));
+
+        // 1月3日(金)の次の平日は1月6日(月)
+        let morning = Cron::daily("7:00", Some("weekdays")).unwrap();
+        assert_eq!(morning.next_after(at(3, 7, 0)), Some(at(6, 7, 0)));
+
+        // 日と曜日の両方があればどちらか
+        let either = Cron::parse("0 9 15 * 7").unwrap();
+        assert_eq!(either.next_after(at(1, 0, 0)), Some(at(5, 9, 0)));
+        assert_eq!(either.next_after(at(12, 9, 0)), Some(at(15, 9, 0)));
+    }
+
+    #[test]
+    fn config() {
+        #[derive(Deserialize)]
+        struct Config {
+            schedule: Vec<ScheduledPost>,
+        }
+        let config: Config = toml::from_str(
+            r#"
+            [[schedule]]
+            at = "7:00"
+            texts = ["おはよう"]
+            [[schedule]]
+            cron = "0 * * * *"
+            texts = ["時報"]
+            visibility = "public"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.schedule.len(), 2);
+
+        let missing_texts = toml::from_str::<Config>("[[schedule]]\nat = \"7:00\"\ntexts = []");
+        assert!(missing_texts.is_err());
+        let both = toml::from_str::<Config>(
+            "[[schedule]]\nat = \"7:00\"\ncron = \"0 7 * * *\"\ntexts = [\"a\"]",
+        );
+        assert!(both.is_err());
+    }
+}
@@ -0,0 +1,231 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+// 偽サーバーを相手にBOTを丸ごと動かして確かめる
+
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use clap::Parser;
+use serde_json::Value;
+
+use crate::{
+    cli::Cli,
+    mock::{self, MockMisskey},
+};
+
+struct TestBot {
+    server: MockMisskey,
+    dir: PathBuf,
+}
+
+impl TestBot {
+    async fn start() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let server = MockMisskey::start().await;
+        let dir = std::env::temp_dir().join(format!(
+            "sango_chan_test_{}_{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let config = format!(
+            r#"
+token = "{}"
+host = "{}"
+admin = "admin"
+
+[wakeup]
+enabled = false
+
+[catchup]
+enabled = false
+
+[rate_limit]
+requests_per_second = 0
+"#,
+            mock::TOKEN,
+            server.base_url()
+        );
+        let config_path = dir.join("config.toml");
+        std::fs::write(&config_path, config).unwrap();
+
+        let cli = Cli::parse_from([
+            "sango_chan".as_ref(),
+            "--config".as_ref(),
+            config_path.as_os_str(),
+            "--data-dir".as_ref(),
+            dir.as_os_str(),
+        ]);
+        tokio::spawn(async move { crate::run(&cli).await });
+        Self { server, dir }
+    }
+
+    async fn replies(&self, count: usize) -> Vec<Value> {
+        self.server.wait_for("/api/notes/create", count).await
+    }
+}
+
+impl Drop for TestBot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[tokio::test]
+async fn replies_to_mention() {
+    let bot = TestBot::start().await;
+    let alice = mock::user("alice", "alice", false);
+    bot.server
+        .push("mention", mock::note("n1", &alice, "@sango こんにちは"))
+        .await;
+
+    let replies = bot.replies(1).await;
+    assert_eq!(replies[0]["replyId"], "n1");
+    assert_eq!(replies[0]["text"], "こんにちは、どうしたの？");
+}
+
+#[tokio::test]
+async fn replies_to_reply() {
+    let bot = TestBot::start().await;
+    let alice = mock::user("alice", "alice", false);
+    let reply = reply_to_sango("n1", &alice, "こんにちは");
+    bot.server.push("reply", reply).await;
+
+    let replies = bot.replies(1).await;
+    assert_eq!(replies[0]["replyId"], "n1");
+    assert_eq!(replies[0]["text"], "こんにちは、どうしたの？");
+}
+
+fn reply_to_sango(id: &str, user: &Value, text: &str) -> Value {
+    let sango = mock::user(mock::SELF_ID, "sango", true);
+    let mut reply = mock::note(id, user, text);
+    reply["replyId"] = "sango_note".into();
+    reply["reply"] = mock::note("sango_note", &sango, "おはよう");
+    reply
+}
+
+#[tokio::test]
+async fn replies_to_reply_without_keyword() {
+    let bot = TestBot::start().await;
+    let alice = mock::user("alice", "alice", false);
+    // 返信先の作者もメンションに入るので、メンションとリプライの両方で来る
+    let mut reply = reply_to_sango("n1", &alice, "今日はいい天気だね");
+    reply["mentions"] = vec![mock::SELF_ID].into();
+    bot.server.push("mention", reply.clone()).await;
+    bot.server.push("reply", reply).await;
+
+    let replies = bot.replies(1).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(bot.server.requests("/api/notes/create").len(), 1);
+    assert_eq!(replies[0]["replyId"], "n1");
+}
+
+#[tokio::test]
+async fn replies_to_chat() {
+    let bot = TestBot::start().await;
+    let alice = mock::user("alice", "alice", false);
+    bot.server
+        .push(
+            "newChatMessage",
+            mock::chat_message("m1", &alice, "こんにちは"),
+        )
+        .await;
+
+    let messages = bot
+        .server
+        .wait_for("/api/chat/messages/create-to-user", 1)
+        .await;
+    assert_eq!(messages[0]["toUserId"], "alice");
+    assert_eq!(messages[0]["text"], "こんにちは、どうしたの？");
+    assert!(bot.server.requests("/api/notes/create").is_empty());
+}
+
+#[tokio::test]
+async fn reacts_to_timeline() {
+    let bot = TestBot::start().await;
+    let alice = mock::user("alice", "alice", false);
+    bot.server
+        .push_note(mock::note("n1", &alice, "つかれた"))
+        .await;
+
+    let replies = bot.replies(1).await;
+    assert_eq!(replies[0]["replyId"], "n1");
+}
+
+#[tokio::test]
+async fn ignores_bots() {
+    let bot = TestBot::start().await;
+    let other_bot = mock::user("bot", "bot", true);
+    bot.server
+        .push("mention", mock::note("n1", &other_bot, "@sango こんにちは"))
+        .await;
+    let alice = mock::user("alice", "alice", false);
+    bot.server
+        .push("mention", mock::note("n2", &alice, "@sango こんにちは"))
+        .await;
+
+    // 後から来たほうにだけ返信する
+    let replies = bot.replies(1).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(bot.server.requests("/api/notes/create").len(), 1);
+    assert_eq!(replies[0]["replyId"], "n2");
+}
+
+#[tokio::test]
+async fn thanks_for_follow() {
+    let bot = TestBot::start().await;
+    bot.server
+        .push("followed", mock::user("alice", "alice", false))
+        .await;
+
+    let notes = bot.replies(1).await;
+    assert!(notes[0]["text"].as_str().unwrap().contains("@alice"));
+}
+
+#[tokio::test]
+async fn follows_back() {
+    let bot = TestBot::start().await;
+    let alice = mock::user("alice", "alice", false);
+    bot.server
+        .push("mention", mock::note("n1", &alice, "@sango フォローして"))
+        .await;
+
+    let follows = bot.server.wait_for("/api/following/create", 1).await;
+    assert_eq!(follows[0]["userId"], "alice");
+    let replies = bot.replies(1).await;
+    assert_eq!(replies[0]["replyId"], "n1");
+}
+
+#[tokio::test]
+async fn follow_back_blocked() {
+    let bot = TestBot::start().await;
+    bot.server.set_response(
+        "/api/following/create",
+        reqwest::StatusCode::BAD_REQUEST,
+        serde_json::json!({
+            "error": {
+                "code": "BLOCKED",
+                "message": "You are blocked by that user.",
+                "id": "c4ab57cc-4e41-45e9-bfd9-584f61e35ce0",
+            },
+        }),
+    );
+    let alice = mock::user("alice", "alice", false);
+    bot.server
+        .push("mention", mock::note("n1", &alice, "@sango フォローして"))
+        .await;
+
+    let replies = bot.replies(1).await;
+    assert!(
+        replies[0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("フォローできない")
+    );
+}
@@ -0,0 +1,102 @@
+// SPDX-FileCopyrightText: 2025 SyoBoN <syobon@syobon.net>
+//
+// SPDX-License-Identifier: UPL-1.0
+
+use chrono::{Local, TimeDelta};
+use serde::Deserialize;
+
+use crate::{Sango, misskey::notes::CreateNote};
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WakeupConfig {
+    pub enabled: bool,
+    pub message: String,
+    // これより短い切断(秒)では投稿しない
+    pub threshold: u64,
+    // 前回の投稿からこれ(秒)も経たずに起動したら、再起動を繰り返しているとみなして投稿しない。0なら毎回投稿する
+    pub crash_loop_window: u64,
+}
+
+impl Default for WakeupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            message: "うーん、うとうとしちゃってたみたい……？".to_owned(),
+            threshold: 600,
+            crash_loop_window: 60,
+        }
+    }
+}
+
+// 接続できたときに、寝ていたことを投稿する
+// downtimeは再接続なら切断されていた時間、起動直後ならNone
+pub async fn announce(
+    sango: &Sango,
+    config: &WakeupConfig,
+    downtime: Option<TimeDelta>,
+) -> anyhow::Result<()> {
+    if !config.enabled || config.message.is_empty() {
+        return Ok(());
+    }
+
+    let now = Local::now();
+    let should_post = match downtime {
+        Some(downtime) => downtime >= seconds(config.threshold),
+        // 起動直後は、再起動を繰り返しているとき以外は投稿する
+        None => sango
+            .savedata
+            .read()
+            .await
+            .last_wakeup()
+            .is_none_or(|last| now - last >= seconds(config.crash_loop_window)),
+    };
+    if !should_post {
+        log::debug!("Skipping the wake-up post.");
+        return Ok(());
+    }
+
+    sango
+        .client
+        .request(CreateNote::new(&config.message))
+        .await?;
+    sango.savedata.write().await.set_last_wakeup(now)?;
+    Ok(())
+}
+
+// 大きすぎる値は、ずっと経たないものとして扱う
+fn seconds(secs: u64) -> TimeDelta {
+    i64::try_from(secs)
+        .ok()
+        .and_then(TimeDelta::try_seconds)
+        .unwrap_or(TimeDelta::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::misskey::fake::{self, FakeClient};
+
+    async fn announce_after(last_wakeup: TimeDelta) -> usize {
+        let client = FakeClient::default();
+        let sango = fake::sango(&client);
+        sango
+            .savedata
+            .write()
+            .await
+            .set_last_wakeup(Local::now() - last_wakeup)
+            .unwrap();
+        announce(&sango, &WakeupConfig::default(), None)
+            .await
+            .unwrap();
+        client.notes().len()
+    }
+
+    #[tokio::test]
+    async fn restart() {
+        // デプロイなどで数分ぶりに起動したときは投稿する
+        assert_eq!(announce_after(TimeDelta::minutes(5)).await, 1);
+        // 再起動を繰り返しているときは投稿しない
+        assert_eq!(announce_after(TimeDelta::seconds(10)).await, 0);
+    }
+}
@@ -5,53 +5,132 @@
 use anyhow::Context;
 use futures::{SinkExt, StreamExt};
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{Value, json};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{
     MaybeTlsStream, WebSocketStream,
-    tungstenite::{Message, Utf8Bytes},
+    tungstenite::{self, Message, Utf8Bytes, http::StatusCode},
 };
 
+use crate::misskey::error::Unauthorized;
+
+// mainチャンネルのID。それ以外のチャンネルはconfig.tomlに書いた順の番号("0"、"1"……)をIDにする
+pub const MAIN_CHANNEL_ID: &str = "main";
+
+// config.tomlの[[channels]]。タイムラインの投稿を受け取るチャンネル
+#[derive(Clone, Deserialize)]
+pub struct ChannelConfig {
+    #[serde(flatten)]
+    pub channel: Channel,
+    // このチャンネルの投稿に使う反応の名前([handlers.note.<名前>]と同じ)。省略時は全部
+    pub handlers: Option<Vec<String>>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(tag = "channel", rename_all = "camelCase")]
+pub enum Channel {
+    HomeTimeline,
+    LocalTimeline,
+    HybridTimeline,
+    GlobalTimeline,
+    // どれか1つのハッシュタグが付いた投稿
+    Hashtag { tags: Vec<String> },
+    Antenna { antenna_id: String },
+    UserList { list_id: String },
+}
+
+impl Channel {
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::HomeTimeline => "homeTimeline",
+            Self::LocalTimeline => "localTimeline",
+            Self::HybridTimeline => "hybridTimeline",
+            Self::GlobalTimeline => "globalTimeline",
+            Self::Hashtag { .. } => "hashtag",
+            Self::Antenna { .. } => "antenna",
+            Self::UserList { .. } => "userList",
+        }
+    }
+
+    fn params(&self) -> Value {
+        match self {
+            // qは「内側のANDを外側でOR」なので、1つずつ包む
+            Self::Hashtag { tags } => {
+                json!({ "q": tags.iter().map(|tag| [tag]).collect::<Vec<_>>() })
+            }
+            Self::Antenna { antenna_id } => json!({ "antennaId": antenna_id }),
+            Self::UserList { list_id } => json!({ "listId": list_id }),
+            _ => json!({}),
+        }
+    }
+}
+
+// 省略時は今まで通りホームタイムラインだけ
+pub fn default_channels() -> Vec<ChannelConfig> {
+    vec![ChannelConfig {
+        channel: Channel::HomeTimeline,
+        handlers: None,
+    }]
+}
+
 pub struct MisskeyWebsocket(WebSocketStream<MaybeTlsStream<TcpStream>>);
 
 impl MisskeyWebsocket {
-    pub async fn new(host: &str, token: &str) -> anyhow::Result<Self> {
-        let mut ws = Self::connect(host, token).await?;
-        ws.subscribe().await?;
+    pub async fn new(
+        base_url: &str,
+        token: &str,
+        channels: &[ChannelConfig],
+    ) -> anyhow::Result<Self> {
+        let mut ws = Self::connect(base_url, token).await?;
+        ws.subscribe(channels).await?;
         Ok(ws)
     }
 
-    async fn connect(host: &str, token: &str) -> anyhow::Result<Self> {
-        let (ws, _) = tokio_tungstenite::connect_async(format!("wss://{host}/streaming?i={token}"))
-            .await
-            .context("Failed to connect to ws")?;
+    async fn connect(base_url: &str, token: &str) -> anyhow::Result<Self> {
+        // https→wss、http→ws
+        let base_url = base_url.replacen("http", "ws", 1);
+        let (ws, _) =
+            match tokio_tungstenite::connect_async(format!("{base_url}/streaming?i={token}")).await
+            {
+                Ok(ws) => ws,
+                Err(tungstenite::Error::Http(resp))
+                    if matches!(
+                        resp.status(),
+                        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+                    ) =>
+                {
+                    return Err(Unauthorized.into());
+                }
+                Err(e) => return Err(e).context("Failed to connect to ws"),
+            };
         Ok(Self(ws))
     }
 
-    async fn subscribe(&mut self) -> anyhow::Result<()> {
+    async fn subscribe(&mut self, channels: &[ChannelConfig]) -> anyhow::Result<()> {
         let main_req = json!({
             "type": "connect",
             "body": {
                 "channel": "main",
-                "id": "main_channel",
+                "id": MAIN_CHANNEL_ID,
             },
         });
-        let home_req = json!({
-            "type": "connect",
-            "body": {
-                "channel": "homeTimeline",
-                "id": "home_channel",
-            },
+        let channel_reqs = channels.iter().enumerate().map(|(i, config)| {
+            json!({
+                "type": "connect",
+                "body": {
+                    "channel": config.channel.name(),
+                    "id": i.to_string(),
+                    "params": config.channel.params(),
+                },
+            })
         });
 
-        self.0
-            .feed(Message::Text(Utf8Bytes::from(main_req.to_string())))
-            .await
-            .context("Failed to send to ws")?;
-        self.0
-            .feed(Message::Text(Utf8Bytes::from(home_req.to_string())))
-            .await
-            .context("Failed to send to ws")?;
+        for req in std::iter::once(main_req).chain(channel_reqs) {
+            self.0
+                .feed(Message::Text(Utf8Bytes::from(req.to_string())))
+                .await
+                .context("Failed to send to ws")?;
+        }
         self.0.flush().await.context("Failed to send to ws")?;
         Ok(())
     }
@@ -105,12 +184,14 @@ pub enum EventBodyType {
     Note,
     Notification, // Unused
     Mention,
-    Reply,  // Unused
-    Renote, // Unused
+    Reply,
+    Renote,
     Follow, // Unused
     Followed,
-    Unfollow,                    // Unused
-    MessagingMessage,            // Unused
+    Unfollow, // Unused(フォローをやめた側にしか来ない)
+    ReceiveFollowRequest,
+    MessagingMessage, // Unused(チャットができる前のメッセージ)
+    NewChatMessage,
     ReadAllNotifications,        // Unused
     UnreadNotification,          // Unused
     UnreadMention,               // Unused
@@ -123,8 +204,48 @@ pub enum EventBodyType {
 
 #[derive(Deserialize)]
 pub struct EventBody {
-    // pub id: String, // Unused
+    // どのチャンネルから来たか
+    pub id: String,
     #[serde(rename = "type")]
     pub event_type: EventBodyType,
     pub body: serde_json::Value,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, misskey::fake};
+
+    #[test]
+    fn channels() {
+        let toml = r#"
+            token = "token"
+            host = "localhost"
+            admin = "admin"
+            [[channels]]
+            channel = "localTimeline"
+            [[channels]]
+            channel = "hashtag"
+            tags = ["a", "b"]
+            handlers = ["call"]
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let [local, hashtag] = &config.channels[..] else {
+            panic!("expected 2 channels");
+        };
+        assert_eq!(local.channel.name(), "localTimeline");
+        assert!(local.handlers.is_none());
+        assert_eq!(hashtag.channel.params(), json!({ "q": [["a"], ["b"]] }));
+        assert_eq!(hashtag.handlers.as_deref(), Some(&["call".to_owned()][..]));
+
+        // 省略時はホームタイムラインだけ
+        let default = fake::config().channels;
+        assert!(matches!(
+            default[..],
+            [ChannelConfig {
+                channel: Channel::HomeTimeline,
+                ..
+            }]
+        ));
+    }
+}